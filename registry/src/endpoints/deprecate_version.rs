@@ -0,0 +1,145 @@
+use crate::{
+    auth::UserId,
+    error::{Error, ErrorResponse},
+    git::{commit_scope_files, refresh_index},
+    search::{latest_entry, update_package},
+    AppState,
+};
+use actix_web::{web, HttpResponse, Responder};
+use git2::Repository;
+use pesde::{
+    names::PackageName,
+    source::{
+        git_index::{read_file, root_tree, GitBasedSource},
+        pesde::{IndexFile, ScopeInfo, SCOPE_INFO_FILE},
+    },
+};
+use semver::VersionReq;
+use serde::Deserialize;
+use std::io::Write;
+
+#[derive(Debug, Deserialize)]
+pub struct DeprecateQuery {
+    #[serde(default)]
+    version: Option<VersionReq>,
+}
+
+async fn set_deprecated(
+    app_state: web::Data<AppState>,
+    name: PackageName,
+    version: Option<VersionReq>,
+    user_id: UserId,
+    message: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let source = app_state.source.lock().await;
+    refresh_index(&app_state, &source).await?;
+
+    let repo = Repository::open_bare(source.path(&app_state.project))?;
+    let gix_repo = gix::open(repo.path())?;
+
+    let gix_tree = root_tree(&gix_repo)?;
+
+    let (scope, name_part) = name.as_str();
+
+    match read_file(&gix_tree, [scope, SCOPE_INFO_FILE])? {
+        Some(info) => {
+            let info: ScopeInfo = toml::de::from_str(&info)?;
+            if !info.owners.contains(&user_id.0) {
+                return Ok(HttpResponse::Forbidden().finish());
+            }
+        }
+        None => return Ok(HttpResponse::NotFound().finish()),
+    }
+
+    let mut entries: IndexFile = match read_file(&gix_tree, [scope, name_part])? {
+        Some(versions) => toml::de::from_str(&versions)?,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    // without a requirement every version is matched, including pre-releases which `*` skips
+    let mut changed = vec![];
+    for (v_id, entry) in entries.iter_mut().filter(|(v_id, _)| {
+        version
+            .as_ref()
+            .is_none_or(|version| version.matches(v_id.version()))
+    }) {
+        entry.deprecated = message.clone();
+        changed.push(v_id.clone());
+    }
+
+    if changed.is_empty() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let index_content = toml::to_string(&entries)?;
+    let mut blob_writer = repo.blob_writer(None)?;
+    blob_writer.write_all(index_content.as_bytes())?;
+
+    let action = if message.is_some() {
+        "deprecate"
+    } else {
+        "undeprecate"
+    };
+
+    commit_scope_files(
+        &app_state,
+        &repo,
+        scope,
+        vec![(name_part, blob_writer.commit()?)],
+        &match version {
+            Some(version) => format!("{action} {name}@{version}"),
+            None => format!("{action} {name}"),
+        },
+    )?;
+
+    update_package(&app_state, &name, latest_entry(&entries).cloned());
+
+    Ok(HttpResponse::Ok().body(format!(
+        "{action}d {}",
+        changed
+            .into_iter()
+            .map(|v_id| format!("{name}@{v_id}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )))
+}
+
+pub async fn deprecate_package_version(
+    app_state: web::Data<AppState>,
+    path: web::Path<PackageName>,
+    query: web::Query<DeprecateQuery>,
+    message: String,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, Error> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "deprecation message must not be empty".to_string(),
+        }));
+    }
+
+    set_deprecated(
+        app_state,
+        path.into_inner(),
+        query.into_inner().version,
+        user_id.into_inner(),
+        Some(message.to_string()),
+    )
+    .await
+}
+
+pub async fn undeprecate_package_version(
+    app_state: web::Data<AppState>,
+    path: web::Path<PackageName>,
+    query: web::Query<DeprecateQuery>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, Error> {
+    set_deprecated(
+        app_state,
+        path.into_inner(),
+        query.into_inner().version,
+        user_id.into_inner(),
+        None,
+    )
+    .await
+}
@@ -1,4 +1,6 @@
+pub mod deprecate_version;
 pub mod package_version;
 pub mod package_versions;
 pub mod publish_version;
 pub mod search;
+pub mod yank_version;
@@ -84,8 +84,14 @@ pub async fn get_package_version(
 
     let Some((v_id, entry, targets)) = ({
         let version = match version {
-            VersionRequest::Latest => match entries.keys().map(|k| k.version()).max() {
-                Some(latest) => latest.clone(),
+            // yanked versions are only the latest if every version is yanked
+            VersionRequest::Latest => match entries
+                .iter()
+                .rev()
+                .find(|(_, entry)| !entry.yanked)
+                .or_else(|| entries.last_key_value())
+            {
+                Some((latest, _)) => latest.version().clone(),
                 None => return Ok(HttpResponse::NotFound().finish()),
             },
             VersionRequest::Specific(version) => version,
@@ -160,6 +166,7 @@ pub async fn get_package_version(
         license: entry.license.clone().unwrap_or_default(),
         authors: entry.authors.clone(),
         repository: entry.repository.clone().map(|url| url.to_string()),
+        deprecated: entry.deprecated.clone(),
     };
 
     let mut value = serde_json::to_value(response)?;
@@ -44,9 +44,13 @@ pub async fn get_package_versions(
                 license: entry.license.unwrap_or_default(),
                 authors: entry.authors.clone(),
                 repository: entry.repository.clone().map(|url| url.to_string()),
+                deprecated: entry.deprecated.clone(),
             });
 
         info.targets.insert(entry.target.into());
+        if info.deprecated.is_none() {
+            info.deprecated = entry.deprecated;
+        }
         info.published_at = info.published_at.max(entry.published_at);
     }
 
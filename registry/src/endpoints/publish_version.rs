@@ -1,8 +1,8 @@
 use crate::{
     auth::UserId,
-    benv,
     error::{Error, ErrorResponse},
-    search::update_version,
+    git::{commit_scope_files, refresh_index},
+    search::update_package,
     storage::StorageImpl,
     AppState,
 };
@@ -11,7 +11,7 @@ use async_compression::Level;
 use convert_case::{Case, Casing};
 use fs_err::tokio as fs;
 use futures::{future::join_all, join};
-use git2::{Remote, Repository, Signature};
+use git2::Repository;
 use pesde::{
     manifest::Manifest,
     source::{
@@ -32,28 +32,6 @@ use std::{
 };
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-fn signature<'a>() -> Signature<'a> {
-    Signature::now(
-        &benv!(required "COMMITTER_GIT_NAME"),
-        &benv!(required "COMMITTER_GIT_EMAIL"),
-    )
-    .unwrap()
-}
-
-fn get_refspec(repo: &Repository, remote: &mut Remote) -> Result<String, git2::Error> {
-    let upstream_branch_buf = repo.branch_upstream_name(repo.head()?.name().unwrap())?;
-    let upstream_branch = upstream_branch_buf.as_str().unwrap();
-
-    let refspec_buf = remote
-        .refspecs()
-        .find(|r| r.direction() == git2::Direction::Fetch && r.dst_matches(upstream_branch))
-        .unwrap()
-        .rtransform(upstream_branch)?;
-    let refspec = refspec_buf.as_str().unwrap();
-
-    Ok(refspec.to_string())
-}
-
 const ADDITIONAL_FORBIDDEN_FILES: &[&str] = &["default.project.json"];
 
 #[derive(Debug, Deserialize, Default)]
@@ -72,7 +50,7 @@ pub async fn publish_package(
     user_id: web::ReqData<UserId>,
 ) -> Result<impl Responder, Error> {
     let source = app_state.source.lock().await;
-    source.refresh(&app_state.project).await.map_err(Box::new)?;
+    refresh_index(&app_state, &source).await?;
     let config = source.config(&app_state.project).await?;
 
     let package_dir = tempfile::tempdir()?;
@@ -387,6 +365,12 @@ pub async fn publish_package(
             docs,
 
             dependencies,
+
+            integrity: Some(format!("{:x}", Sha256::digest(&bytes))),
+
+            yanked: false,
+            yank_reason: None,
+            deprecated: None,
         };
 
         let this_version = entries
@@ -419,11 +403,6 @@ pub async fn publish_package(
             return Ok(HttpResponse::Conflict().finish());
         }
 
-        let mut remote = repo.find_remote("origin")?;
-        let refspec = get_refspec(&repo, &mut remote)?;
-
-        let reference = repo.find_reference(&refspec)?;
-
         {
             let index_content = toml::to_string(&entries)?;
             let mut blob_writer = repo.blob_writer(None)?;
@@ -431,48 +410,18 @@ pub async fn publish_package(
             oids.push((name, blob_writer.commit()?));
         }
 
-        let old_root_tree = reference.peel_to_tree()?;
-        let old_scope_tree = match old_root_tree.get_name(scope) {
-            Some(entry) => Some(repo.find_tree(entry.id())?),
-            None => None,
-        };
-
-        let mut scope_tree = repo.treebuilder(old_scope_tree.as_ref())?;
-        for (file, oid) in oids {
-            scope_tree.insert(file, oid, 0o100644)?;
-        }
-
-        let scope_tree_id = scope_tree.write()?;
-        let mut root_tree = repo.treebuilder(Some(&repo.find_tree(old_root_tree.id())?))?;
-        root_tree.insert(scope, scope_tree_id, 0o040000)?;
-
-        let tree_oid = root_tree.write()?;
-
-        repo.commit(
-            Some("HEAD"),
-            &signature(),
-            &signature(),
+        commit_scope_files(
+            &app_state,
+            &repo,
+            scope,
+            oids,
             &format!(
                 "add {}@{} {}",
                 manifest.name, manifest.version, manifest.target
             ),
-            &repo.find_tree(tree_oid)?,
-            &[&reference.peel_to_commit()?],
         )?;
 
-        let mut push_options = git2::PushOptions::new();
-        let mut remote_callbacks = git2::RemoteCallbacks::new();
-
-        let git_creds = app_state.project.auth_config().git_credentials().unwrap();
-        remote_callbacks.credentials(|_, _, _| {
-            git2::Cred::userpass_plaintext(&git_creds.username, &git_creds.password)
-        });
-
-        push_options.remote_callbacks(remote_callbacks);
-
-        remote.push(&[refspec], Some(&mut push_options))?;
-
-        update_version(&app_state, &manifest.name, new_entry);
+        update_package(&app_state, &manifest.name, Some(new_entry));
     }
 
     let version_id = VersionId::new(manifest.version.clone(), manifest.target.kind());
@@ -93,6 +93,7 @@ pub async fn search_packages(
                 license: entry.license.clone().unwrap_or_default(),
                 authors: entry.authors.clone(),
                 repository: entry.repository.clone().map(|url| url.to_string()),
+                deprecated: entry.deprecated.clone(),
             }
         })
         .collect::<Vec<_>>();
@@ -0,0 +1,130 @@
+use crate::{
+    auth::UserId,
+    endpoints::package_version::TargetRequest,
+    error::Error,
+    git::{commit_scope_files, refresh_index},
+    search::{latest_entry, update_package},
+    AppState,
+};
+use actix_web::{web, HttpResponse, Responder};
+use git2::Repository;
+use pesde::{
+    names::PackageName,
+    source::{
+        git_index::{read_file, root_tree, GitBasedSource},
+        pesde::{IndexFile, ScopeInfo, SCOPE_INFO_FILE},
+    },
+};
+use semver::Version;
+use serde::Deserialize;
+use std::io::Write;
+
+#[derive(Debug, Deserialize)]
+pub struct YankRequest {
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+async fn set_yanked(
+    app_state: web::Data<AppState>,
+    (name, version, target): (PackageName, Version, TargetRequest),
+    user_id: UserId,
+    yanked: bool,
+    reason: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let source = app_state.source.lock().await;
+    refresh_index(&app_state, &source).await?;
+
+    let repo = Repository::open_bare(source.path(&app_state.project))?;
+    let gix_repo = gix::open(repo.path())?;
+
+    let gix_tree = root_tree(&gix_repo)?;
+
+    let (scope, name_part) = name.as_str();
+
+    match read_file(&gix_tree, [scope, SCOPE_INFO_FILE])? {
+        Some(info) => {
+            let info: ScopeInfo = toml::de::from_str(&info)?;
+            if !info.owners.contains(&user_id.0) {
+                return Ok(HttpResponse::Forbidden().finish());
+            }
+        }
+        None => return Ok(HttpResponse::NotFound().finish()),
+    }
+
+    let mut entries: IndexFile = match read_file(&gix_tree, [scope, name_part])? {
+        Some(versions) => toml::de::from_str(&versions)?,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let mut changed = vec![];
+    for (v_id, entry) in entries.iter_mut().filter(|(v_id, _)| {
+        *v_id.version() == version
+            && match &target {
+                TargetRequest::Any => true,
+                TargetRequest::Specific(kind) => v_id.target() == kind,
+            }
+    }) {
+        entry.yanked = yanked;
+        entry.yank_reason = reason.clone();
+        changed.push(v_id.clone());
+    }
+
+    if changed.is_empty() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let index_content = toml::to_string(&entries)?;
+    let mut blob_writer = repo.blob_writer(None)?;
+    blob_writer.write_all(index_content.as_bytes())?;
+
+    let action = if yanked { "yank" } else { "unyank" };
+    let changed = changed
+        .into_iter()
+        .map(|v_id| format!("{name}@{v_id}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    commit_scope_files(
+        &app_state,
+        &repo,
+        scope,
+        vec![(name_part, blob_writer.commit()?)],
+        &format!("{action} {changed}"),
+    )?;
+
+    update_package(&app_state, &name, latest_entry(&entries).cloned());
+
+    Ok(HttpResponse::Ok().body(format!("{action}ed {changed}")))
+}
+
+pub async fn yank_package_version(
+    app_state: web::Data<AppState>,
+    path: web::Path<(PackageName, Version, TargetRequest)>,
+    request: Option<web::Json<YankRequest>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, Error> {
+    set_yanked(
+        app_state,
+        path.into_inner(),
+        user_id.into_inner(),
+        true,
+        request.and_then(|request| request.into_inner().reason),
+    )
+    .await
+}
+
+pub async fn unyank_package_version(
+    app_state: web::Data<AppState>,
+    path: web::Path<(PackageName, Version, TargetRequest)>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, Error> {
+    set_yanked(
+        app_state,
+        path.into_inner(),
+        user_id.into_inner(),
+        false,
+        None,
+    )
+    .await
+}
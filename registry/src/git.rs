@@ -0,0 +1,166 @@
+use crate::{benv, error::Error, AppState};
+use git2::{Oid, Remote, Repository, Signature};
+use pesde::{
+    source::{
+        git_index::GitBasedSource,
+        pesde::{signing, signing::SigningKey, PesdePackageSource},
+    },
+    Project,
+};
+
+pub fn signature<'a>() -> Signature<'a> {
+    Signature::now(
+        &benv!(required "COMMITTER_GIT_NAME"),
+        &benv!(required "COMMITTER_GIT_EMAIL"),
+    )
+    .unwrap()
+}
+
+pub fn get_refspec(repo: &Repository, remote: &mut Remote) -> Result<String, git2::Error> {
+    let upstream_branch_buf = repo.branch_upstream_name(repo.head()?.name().unwrap())?;
+    let upstream_branch = upstream_branch_buf.as_str().unwrap();
+
+    let refspec_buf = remote
+        .refspecs()
+        .find(|r| r.direction() == git2::Direction::Fetch && r.dst_matches(upstream_branch))
+        .unwrap()
+        .rtransform(upstream_branch)?;
+    let refspec = refspec_buf.as_str().unwrap();
+
+    Ok(refspec.to_string())
+}
+
+pub fn commit_message(
+    signing_key: Option<&SigningKey>,
+    message: &str,
+    tree_id: Oid,
+    parent_id: Oid,
+) -> String {
+    match signing_key {
+        Some(key) => key.sign_message(message, &tree_id.to_string(), Some(&parent_id.to_string())),
+        None => message.to_string(),
+    }
+}
+
+pub fn push(project: &Project, remote: &mut Remote, refspec: &str) -> Result<(), git2::Error> {
+    let mut push_options = git2::PushOptions::new();
+    let mut remote_callbacks = git2::RemoteCallbacks::new();
+
+    let git_creds = project.auth_config().git_credentials().unwrap();
+    remote_callbacks.credentials(|_, _, _| {
+        git2::Cred::userpass_plaintext(&git_creds.username, &git_creds.password)
+    });
+
+    push_options.remote_callbacks(remote_callbacks);
+
+    remote.push(&[refspec], Some(&mut push_options))
+}
+
+/// Makes sure the head of the index is signed by the given key, creating a signed commit otherwise
+pub fn sign_index(
+    project: &Project,
+    source: &PesdePackageSource,
+    signing_key: &SigningKey,
+) -> Result<bool, git2::Error> {
+    let repo = Repository::open_bare(source.path(project))?;
+    let mut remote = repo.find_remote("origin")?;
+    let refspec = get_refspec(&repo, &mut remote)?;
+
+    let reference = repo.find_reference(&refspec)?;
+    let head = reference.peel_to_commit()?;
+    let tree_id = head.tree_id();
+    let parent_id = head.parent_id(0).ok().map(|id| id.to_string());
+
+    let public_key = signing_key.public_key();
+    if head
+        .message()
+        .and_then(signing::signature_from_message)
+        .is_some_and(|sig| {
+            signing::verify(
+                [&public_key],
+                &tree_id.to_string(),
+                parent_id.as_deref(),
+                sig,
+            )
+        })
+    {
+        return Ok(false);
+    }
+
+    repo.commit(
+        Some("HEAD"),
+        &signature(),
+        &signature(),
+        &commit_message(Some(signing_key), "sign index", tree_id, head.id()),
+        &head.tree()?,
+        &[&head],
+    )?;
+
+    push(project, &mut remote, &refspec)?;
+
+    Ok(true)
+}
+
+/// Refreshes the index, then makes sure its head is signed, since commits pushed by hand or by
+/// other instances may not be
+pub async fn refresh_index(app_state: &AppState, source: &PesdePackageSource) -> Result<(), Error> {
+    GitBasedSource::refresh(source, &app_state.project)
+        .await
+        .map_err(Box::new)?;
+
+    if let Some(signing_key) = &app_state.signing_key {
+        if sign_index(&app_state.project, source, signing_key)? {
+            log::info!("signed index head");
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the given files into a scope's directory of the index, then commits and pushes the change
+pub fn commit_scope_files(
+    app_state: &AppState,
+    repo: &Repository,
+    scope: &str,
+    files: Vec<(&str, Oid)>,
+    message: &str,
+) -> Result<(), git2::Error> {
+    let mut remote = repo.find_remote("origin")?;
+    let refspec = get_refspec(repo, &mut remote)?;
+
+    let reference = repo.find_reference(&refspec)?;
+
+    let old_root_tree = reference.peel_to_tree()?;
+    let old_scope_tree = match old_root_tree.get_name(scope) {
+        Some(entry) => Some(repo.find_tree(entry.id())?),
+        None => None,
+    };
+
+    let mut scope_tree = repo.treebuilder(old_scope_tree.as_ref())?;
+    for (file, oid) in files {
+        scope_tree.insert(file, oid, 0o100644)?;
+    }
+
+    let scope_tree_id = scope_tree.write()?;
+    let mut root_tree = repo.treebuilder(Some(&repo.find_tree(old_root_tree.id())?))?;
+    root_tree.insert(scope, scope_tree_id, 0o040000)?;
+
+    let tree_oid = root_tree.write()?;
+    let parent = reference.peel_to_commit()?;
+
+    repo.commit(
+        Some("HEAD"),
+        &signature(),
+        &signature(),
+        &commit_message(
+            app_state.signing_key.as_ref(),
+            message,
+            tree_oid,
+            parent.id(),
+        ),
+        &repo.find_tree(tree_oid)?,
+        &[&parent],
+    )?;
+
+    push(&app_state.project, &mut remote, &refspec)
+}
@@ -13,9 +13,12 @@ use actix_web::{
     App, HttpServer,
 };
 use fs_err::tokio as fs;
-use log::info;
+use log::{info, warn};
 use pesde::{
-    source::{pesde::PesdePackageSource, traits::PackageSource},
+    source::{
+        git_index::GitBasedSource,
+        pesde::{signing::SigningKey, PesdePackageSource},
+    },
     AuthConfig, Project,
 };
 use std::{env::current_dir, path::PathBuf};
@@ -23,6 +26,7 @@ use std::{env::current_dir, path::PathBuf};
 mod auth;
 mod endpoints;
 mod error;
+mod git;
 mod package;
 mod search;
 mod storage;
@@ -43,6 +47,7 @@ pub struct AppState {
     pub project: Project,
     pub storage: Storage,
     pub auth: Auth,
+    pub signing_key: Option<SigningKey>,
 
     pub search_reader: tantivy::IndexReader,
     pub search_writer: std::sync::Mutex<tantivy::IndexWriter>,
@@ -91,19 +96,22 @@ async fn run() -> std::io::Result<()> {
         PathBuf::from(benv!("DATA_DIR" => "{CWD}/data").replace("{CWD}", cwd.to_str().unwrap()));
     fs::create_dir_all(&data_dir).await.unwrap();
 
+    let index_url: gix::Url = benv!(required "INDEX_REPO_URL").try_into().unwrap();
+
     let project = Project::new(
         &cwd,
         None::<PathBuf>,
         data_dir.join("project"),
         &cwd,
-        AuthConfig::new().with_git_credentials(Some(gix::sec::identity::Account {
-            username: benv!(required "GIT_USERNAME"),
-            password: benv!(required "GIT_PASSWORD"),
-        })),
+        AuthConfig::new()
+            .with_git_credentials(Some(gix::sec::identity::Account {
+                username: benv!(required "GIT_USERNAME"),
+                password: benv!(required "GIT_PASSWORD"),
+            }))
+            .with_index_hosts(index_url.host()),
     );
-    let source = PesdePackageSource::new(benv!(required "INDEX_REPO_URL").try_into().unwrap());
-    source
-        .refresh(&project)
+    let source = PesdePackageSource::new(index_url);
+    GitBasedSource::refresh(&source, &project)
         .await
         .expect("failed to refresh source");
     let config = source
@@ -111,6 +119,26 @@ async fn run() -> std::io::Result<()> {
         .await
         .expect("failed to get index config");
 
+    let signing_key = benv!("SIGNING_KEY")
+        .ok()
+        .map(|key| SigningKey::from_base64(key).expect("failed to parse signing key"));
+    if let Some(signing_key) = &signing_key {
+        let public_key = signing_key.public_key();
+        info!("signing key: {public_key}");
+
+        if config.signing_keys.is_empty() {
+            warn!("the index config doesn't list any signing_keys, so clients won't verify signatures");
+        } else if !config.signing_keys.contains(&public_key) {
+            panic!("signing key is not listed in the index config's signing_keys, clients would reject its commits");
+        }
+
+        if git::sign_index(&project, &source, signing_key).expect("failed to sign index") {
+            info!("signed index head");
+        }
+    } else if !config.signing_keys.is_empty() {
+        panic!("Environment variable `SIGNING_KEY` must be set, as the index config lists signing_keys");
+    }
+
     let (search_reader, search_writer, query_parser) = make_search(&project, &source).await;
 
     let app_data = web::Data::new(AppState {
@@ -124,6 +152,7 @@ async fn run() -> std::io::Result<()> {
             info!("auth: {auth}");
             auth
         },
+        signing_key,
         source: tokio::sync::Mutex::new(source),
         project,
 
@@ -170,12 +199,36 @@ async fn run() -> std::io::Result<()> {
                             .to(endpoints::package_versions::get_package_versions)
                             .wrap(from_fn(auth::read_mw)),
                     )
+                    .route(
+                        "/packages/{name}/deprecate",
+                        web::put()
+                            .to(endpoints::deprecate_version::deprecate_package_version)
+                            .wrap(from_fn(auth::write_mw)),
+                    )
+                    .route(
+                        "/packages/{name}/deprecate",
+                        web::delete()
+                            .to(endpoints::deprecate_version::undeprecate_package_version)
+                            .wrap(from_fn(auth::write_mw)),
+                    )
                     .route(
                         "/packages/{name}/{version}/{target}",
                         web::get()
                             .to(endpoints::package_version::get_package_version)
                             .wrap(from_fn(auth::read_mw)),
                     )
+                    .route(
+                        "/packages/{name}/{version}/{target}/yank",
+                        web::put()
+                            .to(endpoints::yank_version::yank_package_version)
+                            .wrap(from_fn(auth::write_mw)),
+                    )
+                    .route(
+                        "/packages/{name}/{version}/{target}/yank",
+                        web::delete()
+                            .to(endpoints::yank_version::unyank_package_version)
+                            .wrap(from_fn(auth::write_mw)),
+                    )
                     .service(
                         web::scope("/packages")
                             .app_data(PayloadConfig::new(config.max_archive_size))
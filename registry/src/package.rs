@@ -52,4 +52,6 @@ pub struct PackageResponse {
     pub authors: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repository: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
 }
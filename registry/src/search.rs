@@ -103,9 +103,9 @@ pub async fn make_search(
     let stream = all_packages(source, project).await;
     pin!(stream);
 
-    while let Some((pkg_name, mut file)) = stream.next().await {
-        let Some((_, latest_entry)) = file.pop_last() else {
-            log::warn!("no versions found for {pkg_name}");
+    while let Some((pkg_name, file)) = stream.next().await {
+        let Some(latest_entry) = latest_entry(&file).cloned() else {
+            log::warn!("no unyanked versions found for {pkg_name}");
             continue;
         };
 
@@ -128,20 +128,28 @@ pub async fn make_search(
     (search_reader, search_writer, query_parser)
 }
 
-pub fn update_version(app_state: &AppState, name: &PackageName, entry: IndexFileEntry) {
+/// Returns the entry of the latest version which isn't yanked
+pub fn latest_entry(file: &IndexFile) -> Option<&IndexFileEntry> {
+    file.values().rev().find(|entry| !entry.yanked)
+}
+
+/// Replaces the search document of a package, removing it if there is no entry to show
+pub fn update_package(app_state: &AppState, name: &PackageName, entry: Option<IndexFileEntry>) {
     let mut search_writer = app_state.search_writer.lock().unwrap();
     let schema = search_writer.index().schema();
     let id_field = schema.get_field("id").unwrap();
 
     search_writer.delete_term(Term::from_field_text(id_field, &name.to_string()));
 
-    search_writer.add_document(doc!(
-        id_field => name.to_string(),
-        schema.get_field("scope").unwrap() => name.as_str().0,
-        schema.get_field("name").unwrap() => name.as_str().1,
-        schema.get_field("description").unwrap() => entry.description.unwrap_or_default(),
-        schema.get_field("published_at").unwrap() => DateTime::from_timestamp_secs(entry.published_at.timestamp())
-    )).unwrap();
+    if let Some(entry) = entry {
+        search_writer.add_document(doc!(
+            id_field => name.to_string(),
+            schema.get_field("scope").unwrap() => name.as_str().0,
+            schema.get_field("name").unwrap() => name.as_str().1,
+            schema.get_field("description").unwrap() => entry.description.unwrap_or_default(),
+            schema.get_field("published_at").unwrap() => DateTime::from_timestamp_secs(entry.published_at.timestamp())
+        )).unwrap();
+    }
 
     search_writer.commit().unwrap();
     app_state.search_reader.reload().unwrap();
@@ -0,0 +1,250 @@
+use crate::{
+    names::PackageNames,
+    source::{
+        git_index::{root_tree, GitBasedSource},
+        version_id::VersionId,
+    },
+    util::hash,
+    Project,
+};
+use fs_err::tokio as fs;
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The severity of an advisory
+#[derive(
+    SerializeDisplay, DeserializeFromStr, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub enum Severity {
+    /// A low severity advisory
+    Low,
+    /// A medium severity advisory
+    Medium,
+    /// A high severity advisory
+    High,
+    /// A critical severity advisory
+    Critical,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = errors::SeverityFromStr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            "critical" => Ok(Self::Critical),
+            s => Err(errors::SeverityFromStr::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// A security advisory for a package
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Advisory {
+    /// The identifier of this advisory
+    pub id: String,
+    /// The affected package
+    pub package: PackageNames,
+    /// The affected versions of the package
+    pub versions: VersionReq,
+    /// The severity of this advisory
+    pub severity: Severity,
+    /// A short summary of this advisory
+    pub title: String,
+    /// A description of this advisory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// A URL with more information about this advisory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<url::Url>,
+}
+
+impl Advisory {
+    /// Whether this advisory affects the given package version
+    pub fn affects(&self, name: &PackageNames, version_id: &VersionId) -> bool {
+        self.package == *name && self.versions.matches(version_id.version())
+    }
+}
+
+fn is_advisory_file(name: &str) -> bool {
+    name.ends_with(".toml")
+}
+
+/// A Git repository containing security advisories
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct AdvisoryDatabase {
+    /// The URL of the repository
+    pub repo_url: gix::Url,
+}
+
+impl GitBasedSource for AdvisoryDatabase {
+    fn path(&self, project: &Project) -> PathBuf {
+        project
+            .data_dir
+            .join("advisories")
+            .join(hash(self.repo_url.to_bstring()))
+    }
+
+    fn repo_url(&self) -> &gix::Url {
+        &self.repo_url
+    }
+}
+
+impl AdvisoryDatabase {
+    /// Creates a new advisory database
+    pub fn new(repo_url: gix::Url) -> Self {
+        Self { repo_url }
+    }
+
+    /// Reads every advisory in the database. The database must have been refreshed beforehand
+    pub fn advisories(
+        &self,
+        project: &Project,
+    ) -> Result<Vec<Advisory>, errors::ReadAdvisoriesError> {
+        let repo = gix::open(self.path(project)).map_err(Box::new)?;
+        let tree = root_tree(&repo).map_err(Box::new)?;
+
+        let mut advisories = vec![];
+        let mut trees = vec![(String::new(), tree)];
+
+        while let Some((path, tree)) = trees.pop() {
+            for entry in tree.iter() {
+                let entry = entry?;
+                let entry_path = format!("{path}{}", entry.filename());
+                let object = entry.object()?;
+
+                match object.kind {
+                    gix::object::Kind::Tree => {
+                        trees.push((format!("{entry_path}/"), object.into_tree()));
+                    }
+                    gix::object::Kind::Blob if is_advisory_file(&entry_path) => {
+                        let string =
+                            String::from_utf8(object.into_blob().data.clone()).map_err(|e| {
+                                errors::ReadAdvisoriesError::Utf8(entry_path.clone(), e)
+                            })?;
+
+                        advisories.push(
+                            toml::from_str(&string)
+                                .map_err(|e| errors::ReadAdvisoriesError::Parse(entry_path, e))?,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(advisories)
+    }
+}
+
+/// Reads every advisory in a directory, such as a local checkout of an advisory database
+pub async fn read_advisories_dir<P: AsRef<Path>>(
+    dir: P,
+) -> Result<Vec<Advisory>, errors::ReadAdvisoriesDirError> {
+    let mut advisories = vec![];
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let mut read_dir = fs::read_dir(&dir).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+
+            if entry.file_type().await?.is_dir() {
+                if entry.file_name() != ".git" {
+                    dirs.push(path);
+                }
+                continue;
+            }
+
+            if !is_advisory_file(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+
+            let string = fs::read_to_string(&path).await?;
+            advisories.push(
+                toml::from_str(&string)
+                    .map_err(|e| errors::ReadAdvisoriesDirError::Parse(path, e))?,
+            );
+        }
+    }
+
+    Ok(advisories)
+}
+
+/// Errors that can occur when reading advisories
+pub mod errors {
+    use std::path::PathBuf;
+    use thiserror::Error;
+
+    /// Errors that can occur when parsing a severity from a string
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum SeverityFromStr {
+        /// The severity is unknown
+        #[error("unknown severity {0}")]
+        Unknown(String),
+    }
+
+    /// Errors that can occur when reading advisories from an advisory database
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum ReadAdvisoriesError {
+        /// Error opening the repository
+        #[error("error opening repository")]
+        Open(#[from] Box<gix::open::Error>),
+
+        /// Error getting the root tree
+        #[error("error getting root tree")]
+        Tree(#[from] Box<crate::source::git_index::errors::TreeError>),
+
+        /// Error decoding a tree entry
+        #[error("error decoding tree entry")]
+        Decode(#[from] gix::objs::decode::Error),
+
+        /// Error getting an object
+        #[error("error getting object")]
+        Object(#[from] gix::object::find::existing::Error),
+
+        /// An advisory file is not valid UTF-8
+        #[error("advisory {0} is not valid UTF-8")]
+        Utf8(String, #[source] std::string::FromUtf8Error),
+
+        /// Error parsing an advisory
+        #[error("error parsing advisory {0}")]
+        Parse(String, #[source] toml::de::Error),
+    }
+
+    /// Errors that can occur when reading advisories from a directory
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum ReadAdvisoriesDirError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error parsing an advisory
+        #[error("error parsing advisory {0}")]
+        Parse(PathBuf, #[source] toml::de::Error),
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{
+    lockfile::{DownloadedGraph, Lockfile},
+    source::{
+        fs::{cas_path, write_atomically, FSEntry, PackageFS},
+        git_index::GitBasedSource,
+        traits::PackageRef,
+        PackageSources,
+    },
+    util::hash,
+    Project, LOCKFILE_FILE_NAME,
+};
+use fs_err::tokio as fs;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+use tokio::io::AsyncReadExt;
+
+/// The folder in the CAS which tracks the projects using it
+const PROJECTS_FOLDER: &str = "projects";
+/// The folders in the CAS which contain the cached file systems of packages
+const INDEX_FOLDERS: &[&str] = &["index", "git_index", "wally_index"];
+
+/// Whether the folder contains blobs, i.e. is named after the two character prefix of a hash
+fn is_blob_folder(name: &str) -> bool {
+    name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// A report of what a garbage collection of the CAS removed
+#[derive(Debug, Default, Clone)]
+pub struct CasGcReport {
+    /// The amount of projects which no longer exist and were untracked
+    pub removed_projects: usize,
+    /// The amount of tracked projects whose packages were kept
+    pub tracked_projects: usize,
+    /// The amount of cached package file systems which were removed
+    pub removed_index_files: usize,
+    /// The amount of blobs which were removed
+    pub removed_blobs: usize,
+    /// The amount of bytes which were freed
+    pub freed_bytes: u64,
+    /// The amount of blobs which are still referenced
+    pub kept_blobs: usize,
+    /// The amount of bytes the referenced blobs take up
+    pub kept_bytes: u64,
+}
+
+/// A report of a verification of the CAS
+#[derive(Debug, Default, Clone)]
+pub struct CasVerifyReport {
+    /// The amount of blobs which were checked
+    pub checked_blobs: usize,
+    /// The hashes of the blobs whose contents don't match their hash
+    pub corrupt_blobs: BTreeSet<String>,
+    /// The cached package file systems which can't be read, or reference corrupt or missing blobs
+    pub broken_index_files: BTreeSet<PathBuf>,
+}
+
+/// The files a graph of packages needs to be installed without downloading them
+#[derive(Debug, Default, Clone)]
+pub struct CachedGraph {
+    /// The cached package file systems and blobs in the CAS
+    pub cas_files: BTreeSet<PathBuf>,
+    /// The index repositories the packages were resolved from
+    pub index_repos: BTreeSet<PathBuf>,
+    /// The packages which aren't in the CAS
+    pub uncached: BTreeSet<String>,
+}
+
+/// The hash of the blob at a path relative to the CAS, if the path is one of a blob
+pub fn blob_hash(path: &Path) -> Option<String> {
+    let mut components = path.components();
+    let prefix = components.next()?.as_os_str().to_str()?;
+    let rest = components.next()?.as_os_str().to_str()?;
+
+    (components.next().is_none() && is_blob_folder(prefix)).then(|| format!("{prefix}{rest}"))
+}
+
+/// Hashes the contents of a file
+pub async fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 8 * 1024];
+
+    loop {
+        let bytes_read = file.read(&mut buf).await?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Recursively lists the files in a directory, if it exists
+async fn files_in(dir: PathBuf) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir];
+
+    while let Some(dir) = dirs.pop() {
+        let mut read_dir = match fs::read_dir(&dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Removes a file, ignoring it if it was already removed
+pub(crate) async fn remove_file(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Removes the empty directories inside a directory
+async fn remove_empty_dirs(dir: &Path) -> std::io::Result<bool> {
+    let mut read_dir = match fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+
+    let mut empty = true;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_dir() && Box::pin(remove_empty_dirs(&entry.path())).await? {
+            fs::remove_dir(entry.path()).await?;
+        } else {
+            empty = false;
+        }
+    }
+
+    Ok(empty)
+}
+
+impl Project {
+    /// Records that this project uses its CAS, so that garbage collection keeps its packages
+    pub(crate) async fn track_cas_usage(&self) -> std::io::Result<()> {
+        let projects_dir = self.cas_dir().join(PROJECTS_FOLDER);
+        fs::create_dir_all(&projects_dir).await?;
+
+        let package_dir = self.package_dir().to_string_lossy().to_string();
+        write_atomically(
+            self.cas_dir(),
+            &projects_dir.join(hash(&package_dir)),
+            package_dir,
+        )
+        .await
+    }
+
+    /// Finds the files in the CAS and the index repositories which the packages in the graph need
+    /// to be installed without downloading them
+    pub async fn cached_graph(
+        &self,
+        graph: &DownloadedGraph,
+    ) -> Result<CachedGraph, errors::CachedGraphError> {
+        let cas_dir = self.cas_dir();
+        let mut cached = CachedGraph::default();
+
+        for (name, versions) in graph {
+            for (version_id, node) in versions {
+                let pkg_ref = &node.node.pkg_ref;
+                let Some(index_file) = pkg_ref.cas_index_path(cas_dir) else {
+                    continue;
+                };
+
+                match pkg_ref.source() {
+                    PackageSources::Pesde(source) => {
+                        cached.index_repos.insert(source.path(self));
+                    }
+                    #[cfg(feature = "wally-compat")]
+                    PackageSources::Wally(source) => {
+                        cached.index_repos.insert(source.path(self));
+                    }
+                    _ => {}
+                }
+
+                let contents = match fs::read_to_string(&index_file).await {
+                    Ok(contents) => contents,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        cached.uncached.insert(format!("{name}@{version_id}"));
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                match toml::from_str::<PackageFS>(&contents)
+                    .map_err(|e| errors::CachedGraphError::IndexFile(index_file.clone(), e))?
+                {
+                    PackageFS::CAS(entries) => {
+                        cached
+                            .cas_files
+                            .extend(entries.values().filter_map(|entry| match entry {
+                                FSEntry::File(hash) => Some(cas_path(hash, cas_dir)),
+                                FSEntry::Directory => None,
+                            }));
+                    }
+                    PackageFS::Copy(..) => {}
+                }
+
+                cached.cas_files.insert(index_file);
+            }
+        }
+
+        Ok(cached)
+    }
+
+    /// Removes the blobs and cached package file systems in the CAS which aren't used by any
+    /// tracked project. If `dry_run` is true, nothing is removed, but the report is still
+    /// computed. Otherwise, waits for other processes using the CAS to finish first
+    pub async fn gc_cas(&self, dry_run: bool) -> Result<CasGcReport, errors::CasGcError> {
+        let _lock = self.lock_cas(!dry_run).await?;
+        let cas_dir = self.cas_dir();
+        let mut report = CasGcReport::default();
+
+        // mark the cached file systems of every package in a tracked project's lockfile
+        let mut live_index_files = HashSet::new();
+        let mut keep_all_index_files = false;
+
+        for record in files_in(cas_dir.join(PROJECTS_FOLDER)).await? {
+            let package_dir = PathBuf::from(fs::read_to_string(&record).await?);
+
+            let lockfile = match fs::read_to_string(package_dir.join(LOCKFILE_FILE_NAME)).await {
+                Ok(lockfile) => lockfile,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log::debug!("{} no longer exists, untracking it", package_dir.display());
+                    report.removed_projects += 1;
+                    if !dry_run {
+                        remove_file(&record).await?;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            report.tracked_projects += 1;
+
+            // without the lockfile, the packages the project uses are unknown
+            let lockfile: Lockfile = match toml::from_str(&lockfile) {
+                Ok(lockfile) => lockfile,
+                Err(e) => {
+                    log::warn!(
+                        "failed to read the lockfile of {}, keeping every cached package: {e}",
+                        package_dir.display()
+                    );
+                    keep_all_index_files = true;
+                    continue;
+                }
+            };
+
+            live_index_files.extend(
+                lockfile
+                    .graph
+                    .values()
+                    .flat_map(|versions| versions.values())
+                    .filter_map(|node| node.node.pkg_ref.cas_index_path(cas_dir)),
+            );
+        }
+
+        // sweep the cached file systems which aren't used, and mark the blobs of the rest
+        let mut live_blobs = HashSet::new();
+
+        for folder in INDEX_FOLDERS {
+            for index_file in files_in(cas_dir.join(folder)).await? {
+                let fs = if keep_all_index_files || live_index_files.contains(&index_file) {
+                    let contents = fs::read_to_string(&index_file).await?;
+                    match toml::from_str::<PackageFS>(&contents) {
+                        Ok(fs) => Some(fs),
+                        Err(e) => {
+                            log::warn!(
+                                "removing unreadable index file {}: {e}",
+                                index_file.display()
+                            );
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                match fs {
+                    Some(PackageFS::CAS(entries)) => {
+                        live_blobs.extend(entries.into_values().filter_map(|entry| match entry {
+                            FSEntry::File(hash) => Some(hash),
+                            FSEntry::Directory => None,
+                        }));
+                    }
+                    Some(PackageFS::Copy(..)) => {}
+                    None => {
+                        report.removed_index_files += 1;
+                        if !dry_run {
+                            remove_file(&index_file).await?;
+                        }
+                    }
+                }
+            }
+
+            if !dry_run {
+                remove_empty_dirs(&cas_dir.join(folder)).await?;
+            }
+        }
+
+        // sweep the blobs which aren't used by any cached file system
+        let mut read_dir = match fs::read_dir(cas_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let prefix = entry.file_name().to_string_lossy().to_string();
+            if !is_blob_folder(&prefix) || !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let mut blobs = fs::read_dir(entry.path()).await?;
+            let mut empty = true;
+
+            while let Some(blob) = blobs.next_entry().await? {
+                let hash = format!("{prefix}{}", blob.file_name().to_string_lossy());
+                let size = blob.metadata().await?.len();
+
+                if live_blobs.contains(&hash) {
+                    empty = false;
+                    report.kept_blobs += 1;
+                    report.kept_bytes += size;
+                    continue;
+                }
+
+                report.removed_blobs += 1;
+                report.freed_bytes += size;
+                if !dry_run {
+                    remove_file(&blob.path()).await?;
+                }
+            }
+
+            if empty && !dry_run {
+                fs::remove_dir(entry.path()).await?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Re-hashes every blob in the CAS and finds the cached package file systems which reference
+    /// corrupt or missing blobs. If `repair` is true, these are removed, so that the affected
+    /// packages are downloaded again when next installed, after waiting for other processes using
+    /// the CAS to finish
+    pub async fn verify_cas(
+        &self,
+        repair: bool,
+    ) -> Result<CasVerifyReport, errors::CasVerifyError> {
+        let _lock = self.lock_cas(repair).await?;
+        let cas_dir = self.cas_dir();
+        let mut report = CasVerifyReport::default();
+        let mut valid_blobs = HashSet::new();
+
+        let mut read_dir = match fs::read_dir(cas_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let prefix = entry.file_name().to_string_lossy().to_string();
+            if !is_blob_folder(&prefix) || !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            for blob in files_in(entry.path()).await? {
+                let hash = format!(
+                    "{prefix}{}",
+                    blob.file_name().unwrap_or_default().to_string_lossy()
+                );
+                report.checked_blobs += 1;
+
+                if hash_file(&blob).await? == hash {
+                    valid_blobs.insert(hash);
+                    continue;
+                }
+
+                log::debug!("blob {hash} is corrupt");
+                if repair {
+                    remove_file(&blob).await?;
+                }
+                report.corrupt_blobs.insert(hash);
+            }
+        }
+
+        for folder in INDEX_FOLDERS {
+            for index_file in files_in(cas_dir.join(folder)).await? {
+                let contents = fs::read_to_string(&index_file).await?;
+
+                let broken = match toml::from_str::<PackageFS>(&contents) {
+                    Ok(PackageFS::CAS(entries)) => entries.values().any(|entry| match entry {
+                        FSEntry::File(hash) => !valid_blobs.contains(hash),
+                        FSEntry::Directory => false,
+                    }),
+                    Ok(PackageFS::Copy(..)) => false,
+                    Err(_) => true,
+                };
+
+                if !broken {
+                    continue;
+                }
+
+                if repair {
+                    remove_file(&index_file).await?;
+                }
+                report.broken_index_files.insert(index_file);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Errors that can occur when interacting with the CAS
+pub mod errors {
+    use std::path::PathBuf;
+    use thiserror::Error;
+
+    /// Errors that can occur when garbage collecting the CAS
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum CasGcError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error locking the CAS
+        #[error("error locking CAS")]
+        Lock(#[from] crate::locks::errors::LockError),
+    }
+
+    /// Errors that can occur when finding the cached files of a graph
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum CachedGraphError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error deserializing a cached package file system
+        #[error("error deserializing index file {0}")]
+        IndexFile(PathBuf, #[source] toml::de::Error),
+    }
+
+    /// Errors that can occur when verifying the CAS
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum CasVerifyError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error locking the CAS
+        #[error("error locking CAS")]
+        Lock(#[from] crate::locks::errors::LockError),
+    }
+}
@@ -4,7 +4,9 @@ use anyhow::Context;
 use clap::Args;
 use semver::VersionReq;
 
-use crate::cli::{config::read_config, AnyPackageIdentifier, VersionedPackageName};
+use crate::cli::{
+    config::read_config, warn_about_index_entries, AnyPackageIdentifier, VersionedPackageName,
+};
 use pesde::{
     manifest::target::TargetKind,
     names::PackageNames,
@@ -132,7 +134,7 @@ impl AddCommand {
             .await
             .context("failed to refresh package source")?;
 
-        let Some(version_id) = source
+        let (name, mut versions) = source
             .resolve(
                 &specifier,
                 &project,
@@ -140,16 +142,16 @@ impl AddCommand {
                 &mut HashSet::new(),
             )
             .await
-            .context("failed to resolve package")?
-            .1
-            .pop_last()
-            .map(|(v_id, _)| v_id)
-        else {
+            .context("failed to resolve package")?;
+
+        let Some((version_id, pkg_ref)) = versions.pop_last() else {
             log::error!("no versions found for package {specifier}");
 
             return Ok(());
         };
 
+        warn_about_index_entries(&project, [(&name, &version_id, &pkg_ref)]).await?;
+
         let project_target = manifest.target.kind();
         let mut manifest = toml_edit::DocumentMut::from_str(
             &project
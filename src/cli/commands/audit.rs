@@ -0,0 +1,235 @@
+use anyhow::Context;
+use clap::Args;
+use colored::{ColoredString, Colorize};
+use pesde::{
+    advisories::{read_advisories_dir, Advisory, AdvisoryDatabase, Severity},
+    errors::LockfileReadError,
+    lockfile::DownloadedGraph,
+    names::PackageNames,
+    source::{
+        git_index::GitBasedSource, pesde::PesdePackageSource, traits::PackageSource,
+        version_id::VersionId,
+    },
+    Project,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+};
+
+#[derive(Debug, Args)]
+pub struct AuditCommand {
+    /// A local directory containing advisories to use instead of the indices' advisory databases
+    #[arg(short, long)]
+    advisories: Option<PathBuf>,
+
+    /// The minimum severity of an advisory which makes the command fail
+    #[arg(short, long, default_value = "low")]
+    severity: Severity,
+}
+
+fn colored_severity(severity: Severity) -> ColoredString {
+    let text = severity.to_string().to_uppercase();
+
+    match severity {
+        Severity::Low => text.normal(),
+        Severity::Medium => text.yellow(),
+        Severity::High => text.red(),
+        Severity::Critical => text.red().bold(),
+    }
+}
+
+/// Finds the shortest chain of dependencies from a direct dependency to the given package
+fn dependency_chain<'a>(
+    graph: &'a DownloadedGraph,
+    dependents: &HashMap<(&'a PackageNames, &'a VersionId), Vec<(&'a PackageNames, &'a VersionId)>>,
+    package: (&'a PackageNames, &'a VersionId),
+) -> Vec<(&'a PackageNames, &'a VersionId)> {
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from([package]);
+    let mut visited = HashSet::from([package]);
+
+    while let Some(current) = queue.pop_front() {
+        let is_direct = graph
+            .get(current.0)
+            .and_then(|versions| versions.get(current.1))
+            .is_some_and(|node| node.node.direct.is_some());
+
+        if is_direct {
+            let mut chain = vec![current];
+            let mut current = current;
+            while let Some(next) = previous.get(&current) {
+                chain.push(*next);
+                current = *next;
+            }
+            return chain;
+        }
+
+        for dependent in dependents.get(&current).into_iter().flatten() {
+            if visited.insert(*dependent) {
+                previous.insert(*dependent, current);
+                queue.push_back(*dependent);
+            }
+        }
+    }
+
+    vec![package]
+}
+
+impl AuditCommand {
+    async fn advisories(&self, project: &Project) -> anyhow::Result<Option<Vec<Advisory>>> {
+        if let Some(dir) = &self.advisories {
+            return read_advisories_dir(dir)
+                .await
+                .context("failed to read advisories directory")
+                .map(Some);
+        }
+
+        let manifest = project
+            .deser_manifest()
+            .await
+            .context("failed to read manifest")?;
+
+        let mut databases = HashSet::new();
+
+        for index_url in manifest.indices.values() {
+            let source = PesdePackageSource::new(index_url.clone());
+            PackageSource::refresh(&source, project)
+                .await
+                .context("failed to refresh index")?;
+
+            if let Some(advisories) = source
+                .config(project)
+                .await
+                .context("failed to get index config")?
+                .advisories
+            {
+                databases.insert(AdvisoryDatabase::new(advisories));
+            }
+        }
+
+        if databases.is_empty() {
+            return Ok(None);
+        }
+
+        let mut advisories = vec![];
+
+        for database in databases {
+            database.refresh(project).await.context(format!(
+                "failed to refresh advisory database {}",
+                database.repo_url
+            ))?;
+
+            advisories.extend(database.advisories(project).context(format!(
+                "failed to read advisory database {}",
+                database.repo_url
+            ))?);
+        }
+
+        Ok(Some(advisories))
+    }
+
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        let graph = match project.deser_lockfile().await {
+            Ok(lockfile) => lockfile.graph,
+            Err(LockfileReadError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!(
+                    "no lockfile found, run `{} install` to create it",
+                    env!("CARGO_BIN_NAME")
+                );
+            }
+            Err(e) => return Err(e).context("failed to read lockfile"),
+        };
+
+        let Some(advisories) = self.advisories(&project).await? else {
+            println!(
+                "{}",
+                "none of the project's indices have an advisory database, use --advisories to use a local one"
+                    .yellow()
+            );
+            return Ok(());
+        };
+
+        let mut dependents = HashMap::<_, Vec<_>>::new();
+        for (name, versions) in &graph {
+            for (version_id, node) in versions {
+                for (dep_name, (dep_version_id, _)) in &node.node.dependencies {
+                    dependents
+                        .entry((dep_name, dep_version_id))
+                        .or_default()
+                        .push((name, version_id));
+                }
+            }
+        }
+
+        let mut findings = graph
+            .iter()
+            .flat_map(|(name, versions)| versions.keys().map(move |version_id| (name, version_id)))
+            .flat_map(|(name, version_id)| {
+                advisories
+                    .iter()
+                    .filter(move |advisory| advisory.affects(name, version_id))
+                    .map(move |advisory| (name, version_id, advisory))
+            })
+            .collect::<Vec<_>>();
+
+        if findings.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "no vulnerabilities found in {} advisories",
+                    advisories.len()
+                )
+                .green()
+            );
+            return Ok(());
+        }
+
+        findings.sort_by_key(|(_, _, advisory)| std::cmp::Reverse(advisory.severity));
+
+        for (name, version_id, advisory) in &findings {
+            println!(
+                "{} {}: {}",
+                colored_severity(advisory.severity),
+                advisory.id.bold(),
+                advisory.title
+            );
+            println!("  package: {name}@{version_id}");
+            println!(
+                "  path: {}",
+                dependency_chain(&graph, &dependents, (name, version_id))
+                    .into_iter()
+                    .map(|(name, version_id)| format!("{name}@{version_id}"))
+                    .collect::<Vec<_>>()
+                    .join(" > ")
+            );
+            println!("  affected versions: {}", advisory.versions);
+
+            if let Some(description) = &advisory.description {
+                println!("  {description}");
+            }
+
+            if let Some(url) = &advisory.url {
+                println!("  more information: {url}");
+            }
+
+            println!();
+        }
+
+        let failing = findings
+            .iter()
+            .filter(|(_, _, advisory)| advisory.severity >= self.severity)
+            .count();
+
+        println!("found {} vulnerabilities", findings.len());
+
+        if failing > 0 {
+            anyhow::bail!(
+                "{failing} vulnerabilities have a severity of {} or higher",
+                self.severity
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use super::{CAS_FOLDER, DATA_FOLDER, SCRIPTS_FOLDER};
+use crate::cli::{home_dir, up_to_date_lockfile};
+use anyhow::Context;
+use async_compression::tokio::write::GzipEncoder;
+use clap::Args;
+use fs_err::tokio as fs;
+use pesde::Project;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Args)]
+pub struct ExportCommand {
+    /// The path to write the archive to
+    path: PathBuf,
+}
+
+impl ExportCommand {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
+        let graph = if let Some(lockfile) = up_to_date_lockfile(&project).await? {
+            lockfile.graph
+        } else {
+            anyhow::bail!("outdated lockfile, please run the install command first")
+        };
+
+        let cached = project
+            .cached_graph(&graph)
+            .await
+            .context("failed to find cached files")?;
+
+        if !cached.uncached.is_empty() {
+            anyhow::bail!(
+                "{} aren't in the CAS, please run the install command first",
+                cached.uncached.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        let mut archive = tokio_tar::Builder::new(GzipEncoder::new(
+            fs::File::create(&self.path)
+                .await
+                .context("failed to create archive")?,
+        ));
+
+        for path in &cached.cas_files {
+            let name = Path::new(CAS_FOLDER).join(path.strip_prefix(project.cas_dir())?);
+
+            archive
+                .append_path_with_name(path, name)
+                .await
+                .context(format!("failed to add {} to archive", path.display()))?;
+        }
+
+        for repo in &cached.index_repos {
+            let name = Path::new(DATA_FOLDER).join(repo.strip_prefix(project.data_dir())?);
+
+            archive
+                .append_dir_all(name, repo)
+                .await
+                .context(format!("failed to add {} to archive", repo.display()))?;
+        }
+
+        let scripts_dir = home_dir()?.join("scripts");
+        if scripts_dir.exists() {
+            archive
+                .append_dir_all(SCRIPTS_FOLDER, &scripts_dir)
+                .await
+                .context("failed to add scripts repository to archive")?;
+        }
+
+        let mut encoder = archive
+            .into_inner()
+            .await
+            .context("failed to finish archive")?;
+        encoder
+            .shutdown()
+            .await
+            .context("failed to write archive")?;
+
+        println!(
+            "exported {} cached files and {} index repositories to {}",
+            cached.cas_files.len(),
+            cached.index_repos.len(),
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
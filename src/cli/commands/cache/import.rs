@@ -0,0 +1,191 @@
+use super::{CAS_FOLDER, DATA_FOLDER, SCRIPTS_FOLDER};
+use crate::cli::home_dir;
+use anyhow::Context;
+use async_compression::tokio::bufread::GzipDecoder;
+use clap::Args;
+use fs_err::tokio as fs;
+use futures::StreamExt;
+use pesde::{
+    cas::{blob_hash, hash_file},
+    Project,
+};
+use std::{
+    collections::HashMap,
+    path::{Component, PathBuf},
+};
+use tokio::io::BufReader;
+
+#[derive(Debug, Args)]
+pub struct ImportCommand {
+    /// The path to the archive to import
+    path: PathBuf,
+}
+
+impl ImportCommand {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
+        let home_dir = home_dir()?;
+        let scripts_dir = home_dir.join("scripts");
+        let temp_dir = project.cas_dir().join(".tmp");
+        fs::create_dir_all(&temp_dir)
+            .await
+            .context("failed to create temporary directory")?;
+        // repositories are unpacked here first, so that only complete ones are moved into place
+        let staging_dir = tempfile::Builder::new()
+            .prefix(".import")
+            .tempdir_in(&home_dir)
+            .context("failed to create staging directory")?;
+
+        let mut archive = tokio_tar::Archive::new(GzipDecoder::new(BufReader::new(
+            fs::File::open(&self.path)
+                .await
+                .context("failed to open archive")?,
+        )));
+        let mut entries = archive.entries().context("failed to read archive")?;
+
+        // repositories which already exist are kept as they are, as they're at least as recent
+        let mut existing_repos = HashMap::new();
+        let mut staged_repos = HashMap::new();
+        let mut imported = 0;
+        let mut skipped = 0;
+
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry.context("failed to read archive entry")?;
+            let path = entry
+                .path()
+                .context("invalid archive entry path")?
+                .to_path_buf();
+
+            let entry_type = entry.header().entry_type();
+            if !entry_type.is_file() && !entry_type.is_dir() {
+                anyhow::bail!(
+                    "archive entry {} is not a file or directory",
+                    path.display()
+                );
+            }
+
+            let mut components = path.components().map(|component| match component {
+                Component::Normal(component) => Ok(component),
+                _ => Err(anyhow::anyhow!(
+                    "archive entry {} has an invalid path",
+                    path.display()
+                )),
+            });
+
+            let Some(root) = components.next().transpose()? else {
+                continue;
+            };
+            let rest = components.collect::<Result<PathBuf, _>>()?;
+
+            let repo = match root.to_str() {
+                Some(CAS_FOLDER) => None,
+                Some(DATA_FOLDER) => {
+                    // the folders containing the repositories are created when moving them into place
+                    if rest.iter().count() < 2 {
+                        if entry_type.is_dir() {
+                            continue;
+                        }
+
+                        anyhow::bail!("archive entry {} has an invalid path", path.display());
+                    }
+
+                    let repo = rest.iter().take(2).collect::<PathBuf>();
+                    Some((
+                        project.data_dir().join(&repo),
+                        staging_dir.path().join(DATA_FOLDER).join(repo),
+                    ))
+                }
+                Some(SCRIPTS_FOLDER) => {
+                    Some((scripts_dir.clone(), staging_dir.path().join(SCRIPTS_FOLDER)))
+                }
+                _ => anyhow::bail!("archive entry {} has an invalid path", path.display()),
+            };
+
+            if let Some((repo, staged_repo)) = repo {
+                if *existing_repos
+                    .entry(repo.clone())
+                    .or_insert_with_key(|repo| repo.exists())
+                {
+                    continue;
+                }
+
+                staged_repos.insert(staged_repo, repo);
+
+                if !entry
+                    .unpack_in(staging_dir.path())
+                    .await
+                    .context(format!("failed to unpack {}", path.display()))?
+                {
+                    anyhow::bail!("archive entry {} has an invalid path", path.display());
+                }
+
+                if entry_type.is_file() {
+                    imported += 1;
+                }
+                continue;
+            }
+
+            let destination = project.cas_dir().join(&rest);
+
+            if entry_type.is_dir() {
+                fs::create_dir_all(&destination).await?;
+                continue;
+            }
+
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            // files in the CAS never change, and may be linked into projects
+            if destination.exists() {
+                skipped += 1;
+                continue;
+            }
+
+            let temp_path = tempfile::Builder::new()
+                .make_in(&temp_dir, |_| Ok(()))?
+                .into_temp_path();
+            entry
+                .unpack(&temp_path)
+                .await
+                .context(format!("failed to unpack {}", path.display()))?;
+
+            if let Some(hash) = blob_hash(&rest) {
+                let actual = hash_file(&temp_path)
+                    .await
+                    .context(format!("failed to hash {}", path.display()))?;
+
+                if actual != hash {
+                    anyhow::bail!(
+                        "archive entry {} doesn't match its hash (contents hash to {actual})",
+                        path.display()
+                    );
+                }
+            }
+
+            temp_path
+                .persist(&destination)
+                .context(format!("failed to persist {}", destination.display()))?;
+
+            imported += 1;
+        }
+
+        for (staged_repo, repo) in staged_repos {
+            if let Some(parent) = repo.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            fs::rename(&staged_repo, &repo)
+                .await
+                .context(format!("failed to move {} into place", repo.display()))?;
+        }
+
+        println!("imported {imported} files, {skipped} were already cached");
+
+        Ok(())
+    }
+}
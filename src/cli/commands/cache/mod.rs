@@ -0,0 +1,30 @@
+use clap::Subcommand;
+use pesde::Project;
+
+mod export;
+mod import;
+
+/// The folder in cache archives containing files from the CAS
+const CAS_FOLDER: &str = "cas";
+/// The folder in cache archives containing files from the data directory
+const DATA_FOLDER: &str = "data";
+/// The folder in cache archives containing the scripts repository
+const SCRIPTS_FOLDER: &str = "scripts";
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    /// Writes the cached files the project's lockfile needs to an archive
+    Export(export::ExportCommand),
+
+    /// Restores the cached files from an archive written by the export command
+    Import(import::ImportCommand),
+}
+
+impl CacheCommands {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        match self {
+            CacheCommands::Export(export) => export.run(project).await,
+            CacheCommands::Import(import) => import.run(project).await,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use indicatif::HumanBytes;
+use pesde::Project;
+
+#[derive(Debug, Args)]
+pub struct GcCommand {
+    /// Whether to only report what would be removed, without removing anything
+    #[arg(short, long)]
+    dry_run: bool,
+}
+
+impl GcCommand {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        let report = project
+            .gc_cas(self.dry_run)
+            .await
+            .context("failed to garbage collect CAS")?;
+
+        let verb = if self.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        };
+
+        println!(
+            "{verb} {} blobs and {} cached packages, {} {}",
+            report.removed_blobs,
+            report.removed_index_files,
+            HumanBytes(report.freed_bytes),
+            if self.dry_run { "to free" } else { "freed" }
+        );
+
+        if report.removed_projects > 0 {
+            println!(
+                "{verb} {} projects which no longer exist",
+                report.removed_projects
+            );
+        }
+
+        println!(
+            "{}",
+            format!(
+                "{} blobs ({}) are still used by {} projects",
+                report.kept_blobs,
+                HumanBytes(report.kept_bytes),
+                report.tracked_projects
+            )
+            .dimmed()
+        );
+        println!(
+            "{}",
+            "projects last installed with an older version of pesde aren't tracked, install them again to keep their packages"
+                .dimmed()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use clap::Subcommand;
+use pesde::Project;
+
+mod gc;
+mod verify;
+
+#[derive(Debug, Subcommand)]
+pub enum CasCommands {
+    /// Removes packages from the CAS which aren't used by any project anymore
+    Gc(gc::GcCommand),
+
+    /// Checks that the files in the CAS haven't been modified, optionally removing corrupt ones
+    Verify(verify::VerifyCommand),
+}
+
+impl CasCommands {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        match self {
+            CasCommands::Gc(gc) => gc.run(project).await,
+            CasCommands::Verify(verify) => verify.run(project).await,
+        }
+    }
+}
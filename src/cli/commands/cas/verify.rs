@@ -0,0 +1,53 @@
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use pesde::Project;
+
+#[derive(Debug, Args)]
+pub struct VerifyCommand {
+    /// Whether to remove corrupt blobs and the cached packages which use them, so they're downloaded again
+    #[arg(short, long)]
+    repair: bool,
+}
+
+impl VerifyCommand {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        let report = project
+            .verify_cas(self.repair)
+            .await
+            .context("failed to verify CAS")?;
+
+        for hash in &report.corrupt_blobs {
+            println!("{} {hash}", "corrupt blob".red());
+        }
+
+        for index_file in &report.broken_index_files {
+            println!("{} {}", "broken cached package".red(), index_file.display());
+        }
+
+        if report.corrupt_blobs.is_empty() && report.broken_index_files.is_empty() {
+            println!(
+                "{}",
+                format!("all {} blobs are intact", report.checked_blobs).green()
+            );
+            return Ok(());
+        }
+
+        if !self.repair {
+            anyhow::bail!(
+                "found {} corrupt blobs and {} broken cached packages in {} blobs, run with `--repair` to remove them",
+                report.corrupt_blobs.len(),
+                report.broken_index_files.len(),
+                report.checked_blobs
+            );
+        }
+
+        println!(
+            "removed {} corrupt blobs and {} broken cached packages, reinstall affected projects to download them again",
+            report.corrupt_blobs.len(),
+            report.broken_index_files.len()
+        );
+
+        Ok(())
+    }
+}
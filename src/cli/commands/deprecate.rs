@@ -0,0 +1,101 @@
+use crate::cli::{get_index, VersionedPackageName};
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use pesde::{
+    names::PackageName,
+    source::{pesde::PesdePackageSource, traits::PackageSource},
+    Project,
+};
+use reqwest::{header::AUTHORIZATION, Method, StatusCode};
+use semver::VersionReq;
+
+#[derive(Debug, Args)]
+pub struct DeprecateCommand {
+    /// The package to deprecate, optionally with a version requirement. Deprecates every version if not specified
+    #[arg(index = 1)]
+    package: VersionedPackageName<VersionReq, PackageName>,
+
+    /// The deprecation message, for example what to use instead
+    #[arg(index = 2, required_unless_present = "undo")]
+    message: Option<String>,
+
+    /// Whether to undeprecate the package instead
+    #[arg(short, long, conflicts_with = "message")]
+    undo: bool,
+
+    /// The index to deprecate the package in. Defaults to `default`, or the configured default index if current directory doesn't have a manifest
+    #[arg(short, long)]
+    index: Option<String>,
+}
+
+impl DeprecateCommand {
+    pub async fn run(self, project: Project, reqwest: reqwest::Client) -> anyhow::Result<()> {
+        let VersionedPackageName(name, version) = self.package;
+
+        let index_url = get_index(&project, self.index.as_deref()).await?;
+
+        let source = PesdePackageSource::new(index_url.clone());
+        source
+            .refresh(&project)
+            .await
+            .context("failed to refresh source")?;
+        let config = source
+            .config(&project)
+            .await
+            .context("failed to get source config")?;
+
+        let mut request = reqwest.request(
+            if self.undo {
+                Method::DELETE
+            } else {
+                Method::PUT
+            },
+            format!(
+                "{}/v0/packages/{}/deprecate",
+                config.api(),
+                name.to_string().replace("/", "%2F"),
+            ),
+        );
+
+        if let Some(version) = version {
+            request = request.query(&[("version", version.to_string())]);
+        }
+
+        if let Some(message) = self.message {
+            request = request.body(message);
+        }
+
+        if let Some(token) = project.auth_config().tokens().get(&index_url) {
+            log::debug!("using token for {index_url}");
+            request = request.header(AUTHORIZATION, token);
+        }
+
+        let response = request.send().await.context("failed to send request")?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .context("failed to get response text")?;
+        match status {
+            StatusCode::NOT_FOUND => {
+                println!("{}", "package version not found".red().bold());
+            }
+            StatusCode::FORBIDDEN => {
+                println!(
+                    "{}",
+                    "unauthorized to deprecate under this scope".red().bold()
+                );
+            }
+            code if !code.is_success() => {
+                anyhow::bail!("failed to deprecate package: {code} ({text})");
+            }
+            _ => {
+                println!("{text}");
+            }
+        }
+
+        Ok(())
+    }
+}
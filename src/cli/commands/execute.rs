@@ -1,4 +1,4 @@
-use crate::cli::{config::read_config, progress_bar, VersionedPackageName};
+use crate::cli::{config::read_config, progress_bar, DownloadedBytes, VersionedPackageName};
 use anyhow::Context;
 use clap::Args;
 use fs_err::tokio as fs;
@@ -85,6 +85,11 @@ impl ExecuteCommand {
 
         log::info!("found package {}@{version}", pkg_ref.name);
 
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
         let tmp_dir = project.cas_dir().join(".tmp");
         fs::create_dir_all(&tmp_dir)
             .await
@@ -98,15 +103,19 @@ impl ExecuteCommand {
             project.data_dir(),
             project.cas_dir(),
             project.auth_config().clone(),
-        );
+        )
+        .with_mirrors(project.mirrors().clone())
+        .with_download_config(project.download_config().clone())
+        .with_network_config(project.network_config().clone())
+        .with_link_mode(project.link_mode());
 
         let (fs, target) = source
-            .download(&pkg_ref, &project, &reqwest)
+            .download(&pkg_ref, &project, &reqwest, Arc::new(()))
             .await
             .context("failed to download package")?;
         let bin_path = target.bin_path().context("package has no binary export")?;
 
-        fs.write_to(tempdir.path(), project.cas_dir(), true)
+        fs.write_to(tempdir.path(), project.cas_dir(), project.link_mode())
             .await
             .context("failed to write package contents")?;
 
@@ -117,8 +126,17 @@ impl ExecuteCommand {
             .await
             .context("failed to build dependency graph")?;
 
+        let downloaded = Arc::new(DownloadedBytes::default());
+
         let (rx, downloaded_graph) = project
-            .download_graph(&graph, &mut refreshed_sources, &reqwest, true, true)
+            .download_graph(
+                &graph,
+                &mut refreshed_sources,
+                &reqwest,
+                true,
+                true,
+                downloaded.clone(),
+            )
             .await
             .context("failed to download dependencies")?;
 
@@ -129,6 +147,7 @@ impl ExecuteCommand {
             "📥 ".to_string(),
             "downloading dependencies".to_string(),
             "downloaded dependencies".to_string(),
+            Some(downloaded),
         )
         .await?;
 
@@ -150,6 +169,7 @@ impl ExecuteCommand {
                         )
                     })
                     .collect(),
+                &mut Default::default(),
             )
             .await
             .context("failed to link dependencies")?;
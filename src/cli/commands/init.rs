@@ -15,7 +15,7 @@ use fs_err::tokio as fs;
 #[derive(Debug, Args)]
 pub struct InitCommand {}
 
-fn script_contents(path: &Path) -> String {
+pub(crate) fn script_contents(path: &Path) -> String {
     format!(
         r#"local process = require("@lune/process")   
 local home_dir = if process.os == "windows" then process.env.userprofile else process.env.HOME
@@ -1,6 +1,6 @@
 use crate::cli::{
     bin_dir, files::make_executable, progress_bar, repos::update_scripts, run_on_workspace_members,
-    up_to_date_lockfile,
+    up_to_date_lockfile, warn_about_index_entries, DownloadedBytes,
 };
 use anyhow::Context;
 use clap::Args;
@@ -9,12 +9,13 @@ use fs_err::tokio as fs;
 use futures::future::try_join_all;
 use indicatif::MultiProgress;
 use pesde::{
-    lockfile::Lockfile,
+    install_state::InstallState,
+    lockfile::{DependencyGraph, DownloadedGraph, Lockfile},
     manifest::{target::TargetKind, DependencyType},
     Project, MANIFEST_FILE_NAME,
 };
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     sync::Arc,
 };
 
@@ -96,6 +97,12 @@ impl InstallCommand {
         multi: MultiProgress,
         reqwest: reqwest::Client,
     ) -> anyhow::Result<()> {
+        let _lock = project.lock().await.context("failed to lock project")?;
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
         let mut refreshed_sources = HashSet::new();
 
         let manifest = project
@@ -145,37 +152,6 @@ impl InstallCommand {
                 .on_bright_black()
         );
 
-        println!("{} ❌ removing current package folders", job(1));
-
-        {
-            let mut deleted_folders = HashMap::new();
-
-            for target_kind in TargetKind::VARIANTS {
-                let folder = manifest.target.kind().packages_folder(target_kind);
-                let package_dir = project.package_dir();
-
-                deleted_folders
-                    .entry(folder.to_string())
-                    .or_insert_with(|| async move {
-                        log::debug!("deleting the {folder} folder");
-
-                        if let Some(e) = fs::remove_dir_all(package_dir.join(&folder))
-                            .await
-                            .err()
-                            .filter(|e| e.kind() != std::io::ErrorKind::NotFound)
-                        {
-                            return Err(e).context(format!("failed to remove the {folder} folder"));
-                        };
-
-                        Ok(())
-                    });
-            }
-
-            try_join_all(deleted_folders.into_values())
-                .await
-                .context("failed to remove package folders")?;
-        }
-
         let old_graph = lockfile.map(|lockfile| {
             lockfile
                 .graph
@@ -192,37 +168,133 @@ impl InstallCommand {
                 .collect()
         });
 
-        println!("{} 📦 building dependency graph", job(2));
+        println!("{} 📦 building dependency graph", job(1));
 
         let graph = project
             .dependency_graph(old_graph.as_ref(), &mut refreshed_sources, false)
             .await
             .context("failed to build dependency graph")?;
 
+        println!("{} ❌ removing outdated packages", job(2));
+
+        let (mut install_state, kept_graph) = match project
+            .deser_install_state()
+            .await
+            .context("failed to read install state")?
+        {
+            Some(mut install_state) => {
+                let kept_graph = project
+                    .remove_outdated_packages(&mut install_state, &graph, self.prod)
+                    .await
+                    .context("failed to remove outdated packages")?;
+
+                (install_state, kept_graph)
+            }
+            // without knowing what is installed, start from scratch
+            None => {
+                let mut deleted_folders = HashMap::new();
+
+                for target_kind in TargetKind::VARIANTS {
+                    let folder = manifest.target.kind().packages_folder(target_kind);
+                    let package_dir = project.package_dir();
+
+                    deleted_folders
+                        .entry(folder.to_string())
+                        .or_insert_with(|| async move {
+                            log::debug!("deleting the {folder} folder");
+
+                            if let Some(e) = fs::remove_dir_all(package_dir.join(&folder))
+                                .await
+                                .err()
+                                .filter(|e| e.kind() != std::io::ErrorKind::NotFound)
+                            {
+                                return Err(e)
+                                    .context(format!("failed to remove the {folder} folder"));
+                            };
+
+                            Ok(())
+                        });
+                }
+
+                try_join_all(deleted_folders.into_values())
+                    .await
+                    .context("failed to remove package folders")?;
+
+                (InstallState::default(), DownloadedGraph::new())
+            }
+        };
+
         update_scripts_handle.await??;
 
+        let changed_graph: DependencyGraph = graph
+            .iter()
+            .map(|(name, versions)| {
+                (
+                    name.clone(),
+                    versions
+                        .iter()
+                        .filter(|(version_id, _)| {
+                            !kept_graph
+                                .get(name)
+                                .is_some_and(|versions| versions.contains_key(*version_id))
+                        })
+                        .map(|(version_id, node)| (version_id.clone(), node.clone()))
+                        .collect::<BTreeMap<_, _>>(),
+                )
+            })
+            .filter(|(_, versions)| !versions.is_empty())
+            .collect();
+
         let downloaded_graph = {
+            let downloaded = Arc::new(DownloadedBytes::default());
             let (rx, downloaded_graph) = project
-                .download_graph(&graph, &mut refreshed_sources, &reqwest, self.prod, true)
+                .download_graph(
+                    &changed_graph,
+                    &mut refreshed_sources,
+                    &reqwest,
+                    self.prod,
+                    true,
+                    downloaded.clone(),
+                )
                 .await
                 .context("failed to download dependencies")?;
 
             progress_bar(
-                graph.values().map(|versions| versions.len() as u64).sum(),
+                changed_graph
+                    .values()
+                    .map(|versions| versions.len() as u64)
+                    .sum(),
                 rx,
                 &multi,
                 format!("{} 📥 ", job(3)),
                 "downloading dependencies".to_string(),
                 "downloaded dependencies".to_string(),
+                Some(downloaded),
             )
             .await?;
 
-            Arc::into_inner(downloaded_graph)
+            let mut downloaded_graph = Arc::into_inner(downloaded_graph)
                 .unwrap()
                 .into_inner()
-                .unwrap()
+                .unwrap();
+
+            for (name, versions) in kept_graph {
+                downloaded_graph.entry(name).or_default().extend(versions);
+            }
+
+            downloaded_graph
         };
 
+        warn_about_index_entries(
+            &project,
+            downloaded_graph.iter().flat_map(|(name, versions)| {
+                versions
+                    .iter()
+                    .map(move |(version_id, node)| (name, version_id, &node.node.pkg_ref))
+            }),
+        )
+        .await?;
+
         let filtered_graph = if self.prod {
             downloaded_graph
                 .clone()
@@ -243,7 +315,7 @@ impl InstallCommand {
         #[cfg(feature = "patches")]
         {
             let rx = project
-                .apply_patches(&filtered_graph)
+                .apply_patches(&filtered_graph, &install_state)
                 .await
                 .context("failed to apply patches")?;
 
@@ -254,6 +326,7 @@ impl InstallCommand {
                 format!("{} 🩹 ", job(4)),
                 "applying patches".to_string(),
                 "applied patches".to_string(),
+                None,
             )
             .await?;
         }
@@ -310,12 +383,21 @@ impl InstallCommand {
         .await?;
 
         project
-            .link_dependencies(&filtered_graph)
+            .link_dependencies(&filtered_graph, &mut install_state)
             .await
             .context("failed to link dependencies")?;
 
         println!("{} 🧹 finishing up", job(JOBS));
 
+        install_state.packages = project
+            .installed_packages(&filtered_graph)
+            .await
+            .context("failed to describe installed packages")?;
+        project
+            .write_install_state(&install_state)
+            .await
+            .context("failed to write install state")?;
+
         project
             .write_lockfile(Lockfile {
                 name: manifest.name,
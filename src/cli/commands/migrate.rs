@@ -0,0 +1,234 @@
+use std::{collections::HashSet, path::Path, str::FromStr};
+
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use inquire::validator::Validation;
+
+use pesde::{
+    errors::ManifestReadError,
+    manifest::target::TargetKind,
+    names::{wally::WallyPackageName, PackageName},
+    scripts::ScriptName,
+    source::{
+        pesde::{errors::ResolveError, specifier::PesdeDependencySpecifier, PesdePackageSource},
+        traits::PackageSource,
+        wally::{
+            compat_util::{find_rojo_lib_path, WALLY_MANIFEST_FILE_NAME},
+            manifest::{Realm, WallyManifest},
+        },
+    },
+    Project, DEFAULT_INDEX_NAME,
+};
+
+use super::init::script_contents;
+use crate::cli::config::read_config;
+use fs_err::tokio as fs;
+
+#[derive(Debug, Args)]
+pub struct MigrateCommand {}
+
+/// Converts a Wally package name into the equivalent pesde package name
+fn pesde_name(name: &WallyPackageName) -> String {
+    let (scope, name) = name.as_str();
+
+    format!("{}/{}", scope.replace('-', "_"), name.replace('-', "_"))
+}
+
+impl MigrateCommand {
+    pub async fn run(self, project: Project) -> anyhow::Result<()> {
+        match project.read_manifest().await {
+            Ok(_) => {
+                println!("{}", "project already initialized".red());
+                return Ok(());
+            }
+            Err(ManifestReadError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        };
+
+        let wally_manifest =
+            match fs::read_to_string(project.package_dir().join(WALLY_MANIFEST_FILE_NAME)).await {
+                Ok(manifest) => manifest,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    println!("{}", format!("no {WALLY_MANIFEST_FILE_NAME} found").red());
+                    return Ok(());
+                }
+                Err(e) => return Err(e).context("failed to read wally manifest"),
+            };
+        let wally_manifest: WallyManifest =
+            toml::from_str(&wally_manifest).context("failed to parse wally manifest")?;
+        let wally_package = &wally_manifest.package;
+
+        let mut manifest = toml_edit::DocumentMut::new();
+
+        let name = pesde_name(&wally_package.name);
+        manifest["name"] = toml_edit::value(match PackageName::from_str(&name) {
+            Ok(name) => name.to_string(),
+            Err(e) => {
+                println!(
+                    "{}",
+                    format!("{name} is not a valid pesde package name: {e}").yellow()
+                );
+
+                inquire::Text::new("What is the name of the project?")
+                    .with_validator(|name: &str| {
+                        Ok(match PackageName::from_str(name) {
+                            Ok(_) => Validation::Valid,
+                            Err(e) => Validation::Invalid(e.to_string().into()),
+                        })
+                    })
+                    .prompt()
+                    .unwrap()
+            }
+        });
+        manifest["version"] = toml_edit::value(wally_package.version.to_string());
+
+        if let Some(description) = &wally_package.description {
+            manifest["description"] = toml_edit::value(description);
+        }
+
+        if let Some(license) = &wally_package.license {
+            manifest["license"] = toml_edit::value(license);
+        }
+
+        if !wally_package.authors.is_empty() {
+            manifest["authors"] =
+                toml_edit::value(wally_package.authors.iter().collect::<toml_edit::Array>());
+        }
+
+        if wally_package.private {
+            manifest["private"] = toml_edit::value(true);
+        }
+
+        let target_kind = match wally_package.realm {
+            Realm::Shared => TargetKind::Roblox,
+            Realm::Server => TargetKind::RobloxServer,
+        };
+
+        let target = manifest["target"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()));
+        target["environment"] = toml_edit::value(target_kind.to_string());
+
+        if let Some(lib) = find_rojo_lib_path(project.package_dir())
+            .await
+            .context("failed to find lib path")?
+        {
+            target["lib"] = toml_edit::value(lib.as_str());
+
+            if let Some(root) = lib.components().next() {
+                target["build_files"] =
+                    toml_edit::value(toml_edit::Array::from_iter([root.as_str()]));
+            }
+        } else {
+            log::warn!("no lib path found in the Rojo project, leaving the target without one");
+        }
+
+        let folder = project
+            .package_dir()
+            .join(concat!(".", env!("CARGO_PKG_NAME")));
+        fs::create_dir_all(&folder)
+            .await
+            .context("failed to create scripts folder")?;
+
+        fs::write(
+            folder.join(format!("{}.luau", ScriptName::RobloxSyncConfigGenerator)),
+            script_contents(Path::new(&format!(
+                "lune/rojo/{}.luau",
+                ScriptName::RobloxSyncConfigGenerator
+            ))),
+        )
+        .await
+        .context("failed to write sync config generator script file")?;
+
+        manifest["scripts"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()))
+            [&ScriptName::RobloxSyncConfigGenerator.to_string()] = toml_edit::value(format!(
+            concat!(".", env!("CARGO_PKG_NAME"), "/{}.luau"),
+            ScriptName::RobloxSyncConfigGenerator
+        ));
+
+        let default_index = read_config().await?.default_index;
+
+        manifest["indices"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()))
+            [DEFAULT_INDEX_NAME] = toml_edit::value(default_index.to_bstring().to_string());
+        manifest["wally_indices"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()))
+            [DEFAULT_INDEX_NAME] = toml_edit::value(wally_package.registry.to_string());
+
+        let place = manifest["place"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()));
+        place["shared"] = toml_edit::value("game.ReplicatedStorage.Packages");
+        place["server"] = toml_edit::value("game.ServerScriptService.Packages");
+
+        let source = PesdePackageSource::new(default_index);
+        source
+            .refresh(&project)
+            .await
+            .context("failed to refresh package source")?;
+
+        let mut aliases = HashSet::new();
+
+        for (deps, dependency_key) in [
+            (&wally_manifest.dependencies, "dependencies"),
+            (&wally_manifest.server_dependencies, "dependencies"),
+            (&wally_manifest.dev_dependencies, "dev_dependencies"),
+        ] {
+            for (alias, spec) in deps {
+                if !aliases.insert(alias) {
+                    anyhow::bail!("alias {alias} is used by multiple dependencies");
+                }
+
+                let pesde_spec = match PackageName::from_str(&pesde_name(&spec.name)).ok() {
+                    Some(name) => {
+                        let specifier = PesdeDependencySpecifier {
+                            name,
+                            version: spec.version.clone(),
+                            index: None,
+                            target: None,
+                        };
+
+                        match source
+                            .resolve(&specifier, &project, target_kind, &mut HashSet::new())
+                            .await
+                        {
+                            Ok((_, versions)) if !versions.is_empty() => Some(specifier),
+                            Ok(_) | Err(ResolveError::NotFound(_)) => None,
+                            Err(e) => {
+                                return Err(e).context(format!(
+                                    "failed to look up {} on the pesde index",
+                                    specifier.name
+                                ))
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                let field = &mut manifest[dependency_key]
+                    .or_insert(toml_edit::Item::Table(toml_edit::Table::new()))[alias];
+
+                match pesde_spec {
+                    Some(pesde_spec) => {
+                        field["name"] = toml_edit::value(pesde_spec.name.to_string());
+                        field["version"] = toml_edit::value(spec.version.to_string());
+
+                        println!(
+                            "migrated {} to pesde {}@{}",
+                            spec.name, pesde_spec.name, spec.version
+                        );
+                    }
+                    None => {
+                        field["wally"] = toml_edit::value(spec.name.to_string());
+                        field["version"] = toml_edit::value(spec.version.to_string());
+                    }
+                }
+            }
+        }
+
+        project.write_manifest(manifest.to_string()).await?;
+
+        println!("{}", "migrated project".green());
+        println!(
+            "dependencies will now be installed into the roblox_packages and roblox_server_packages folders, update your Rojo project files accordingly and run `{} install`",
+            env!("CARGO_BIN_NAME")
+        );
+
+        Ok(())
+    }
+}
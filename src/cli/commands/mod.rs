@@ -2,11 +2,17 @@ use indicatif::MultiProgress;
 use pesde::Project;
 
 mod add;
+mod audit;
 mod auth;
+mod cache;
+mod cas;
 mod config;
+mod deprecate;
 mod execute;
 mod init;
 mod install;
+#[cfg(feature = "wally-compat")]
+mod migrate;
 mod outdated;
 #[cfg(feature = "patches")]
 mod patch;
@@ -19,6 +25,9 @@ mod self_install;
 #[cfg(feature = "version-management")]
 mod self_upgrade;
 mod update;
+mod vendor;
+mod verify;
+mod yank;
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Subcommand {
@@ -29,9 +38,21 @@ pub enum Subcommand {
     #[command(subcommand)]
     Config(config::ConfigCommands),
 
+    /// CAS-related commands
+    #[command(subcommand)]
+    Cas(cas::CasCommands),
+
+    /// Commands for moving cached packages between machines, such as CI runs
+    #[command(subcommand)]
+    Cache(cache::CacheCommands),
+
     /// Initializes a manifest file in the current directory
     Init(init::InitCommand),
 
+    /// Converts a Wally project in the current directory into a pesde project
+    #[cfg(feature = "wally-compat")]
+    Migrate(migrate::MigrateCommand),
+
     /// Runs a script, an executable package, or a file with Lune
     Run(run::RunCommand),
 
@@ -66,6 +87,21 @@ pub enum Subcommand {
     /// Checks for outdated dependencies
     Outdated(outdated::OutdatedCommand),
 
+    /// Yanks a package version from the registry, so that it's no longer resolved for new installs
+    Yank(yank::YankCommand),
+
+    /// Deprecates a package or some of its versions in the registry
+    Deprecate(deprecate::DeprecateCommand),
+
+    /// Checks the dependencies in the lockfile against security advisories
+    Audit(audit::AuditCommand),
+
+    /// Copies the dependencies into the project, so that they can be installed without downloading them
+    Vendor(vendor::VendorCommand),
+
+    /// Checks the installed dependencies for files which differ from their original contents
+    Verify(verify::VerifyCommand),
+
     /// Executes a binary package without needing to be run in a project directory
     #[clap(name = "x", visible_alias = "execute", visible_alias = "exec")]
     Execute(execute::ExecuteCommand),
@@ -81,7 +117,11 @@ impl Subcommand {
         match self {
             Subcommand::Auth(auth) => auth.run(project, reqwest).await,
             Subcommand::Config(config) => config.run().await,
+            Subcommand::Cas(cas) => cas.run(project).await,
+            Subcommand::Cache(cache) => cache.run(project).await,
             Subcommand::Init(init) => init.run(project).await,
+            #[cfg(feature = "wally-compat")]
+            Subcommand::Migrate(migrate) => migrate.run(project).await,
             Subcommand::Run(run) => run.run(project).await,
             Subcommand::Install(install) => install.run(project, multi, reqwest).await,
             Subcommand::Publish(publish) => publish.run(project, reqwest).await,
@@ -96,6 +136,11 @@ impl Subcommand {
             Subcommand::Add(add) => add.run(project).await,
             Subcommand::Update(update) => update.run(project, multi, reqwest).await,
             Subcommand::Outdated(outdated) => outdated.run(project).await,
+            Subcommand::Yank(yank) => yank.run(project, reqwest).await,
+            Subcommand::Deprecate(deprecate) => deprecate.run(project, reqwest).await,
+            Subcommand::Audit(audit) => audit.run(project).await,
+            Subcommand::Vendor(vendor) => vendor.run(project, reqwest).await,
+            Subcommand::Verify(verify) => verify.run(project, reqwest).await,
             Subcommand::Execute(execute) => execute.run(project, multi, reqwest).await,
         }
     }
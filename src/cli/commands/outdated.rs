@@ -1,4 +1,4 @@
-use crate::cli::up_to_date_lockfile;
+use crate::cli::{up_to_date_lockfile, warn_about_index_entries};
 use anyhow::Context;
 use clap::Args;
 use futures::future::try_join_all;
@@ -52,6 +52,16 @@ impl OutdatedCommand {
         )
         .await?;
 
+        warn_about_index_entries(
+            &project,
+            graph.iter().flat_map(|(name, versions)| {
+                versions
+                    .iter()
+                    .map(move |(version_id, node)| (name, version_id, &node.node.pkg_ref))
+            }),
+        )
+        .await?;
+
         let refreshed_sources = Arc::new(Mutex::new(refreshed_sources));
 
         if try_join_all(
@@ -6,11 +6,13 @@ use fs_err::tokio as fs;
 use pesde::{
     patches::setup_patches_repo,
     source::{
+        fs::LinkMode,
         refs::PackageRefs,
         traits::{PackageRef, PackageSource},
     },
     Project, MANIFEST_FILE_NAME,
 };
+use std::sync::Arc;
 
 #[derive(Debug, Args)]
 pub struct PatchCommand {
@@ -49,10 +51,10 @@ impl PatchCommand {
         fs::create_dir_all(&directory).await?;
 
         source
-            .download(&node.node.pkg_ref, &project, &reqwest)
+            .download(&node.node.pkg_ref, &project, &reqwest, Arc::new(()))
             .await?
             .0
-            .write_to(&directory, project.cas_dir(), false)
+            .write_to(&directory, project.cas_dir(), LinkMode::Copy)
             .await
             .context("failed to write package contents")?;
 
@@ -3,7 +3,58 @@ use anyhow::Context;
 use clap::Args;
 use fs_err::tokio as fs;
 use pesde::{names::PackageNames, patches::create_patch, source::version_id::VersionId, Project};
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Turns a patching environment into a patch file, and adds it to the manifest
+pub async fn commit_patch(
+    project: &Project,
+    name: &PackageNames,
+    version_id: &VersionId,
+    directory: &Path,
+) -> anyhow::Result<()> {
+    let mut manifest = toml_edit::DocumentMut::from_str(
+        &project
+            .read_manifest()
+            .await
+            .context("failed to read manifest")?,
+    )
+    .context("failed to parse manifest")?;
+
+    let patch = create_patch(directory).context("failed to create patch")?;
+    fs::remove_dir_all(directory)
+        .await
+        .context("failed to remove patch directory")?;
+
+    let patches_dir = project.package_dir().join("patches");
+    fs::create_dir_all(&patches_dir)
+        .await
+        .context("failed to create patches directory")?;
+
+    let patch_file_name = format!("{}-{}.patch", name.escaped(), version_id.escaped());
+
+    let patch_file = patches_dir.join(&patch_file_name);
+    if patch_file.exists() {
+        anyhow::bail!("patch file already exists: {}", patch_file.display());
+    }
+
+    fs::write(&patch_file, patch)
+        .await
+        .context("failed to write patch file")?;
+
+    manifest["patches"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()))
+        [&name.to_string()][&version_id.to_string()] =
+        toml_edit::value(format!("patches/{patch_file_name}"));
+
+    project
+        .write_manifest(manifest.to_string())
+        .await
+        .context("failed to write manifest")?;
+
+    Ok(())
+}
 
 #[derive(Debug, Args)]
 pub struct PatchCommitCommand {
@@ -48,43 +99,7 @@ impl PatchCommitCommand {
             .and_then(|versions| versions.get(&version_id))
             .context("package not found in graph")?;
 
-        let mut manifest = toml_edit::DocumentMut::from_str(
-            &project
-                .read_manifest()
-                .await
-                .context("failed to read manifest")?,
-        )
-        .context("failed to parse manifest")?;
-
-        let patch = create_patch(&self.directory).context("failed to create patch")?;
-        fs::remove_dir_all(self.directory)
-            .await
-            .context("failed to remove patch directory")?;
-
-        let patches_dir = project.package_dir().join("patches");
-        fs::create_dir_all(&patches_dir)
-            .await
-            .context("failed to create patches directory")?;
-
-        let patch_file_name = format!("{}-{}.patch", name.escaped(), version_id.escaped());
-
-        let patch_file = patches_dir.join(&patch_file_name);
-        if patch_file.exists() {
-            anyhow::bail!("patch file already exists: {}", patch_file.display());
-        }
-
-        fs::write(&patch_file, patch)
-            .await
-            .context("failed to write patch file")?;
-
-        manifest["patches"].or_insert(toml_edit::Item::Table(toml_edit::Table::new()))
-            [&name.to_string()][&version_id.to_string()] =
-            toml_edit::value(format!("patches/{patch_file_name}"));
-
-        project
-            .write_manifest(manifest.to_string())
-            .await
-            .context("failed to write manifest")?;
+        commit_patch(&project, &name, &version_id, &self.directory).await?;
 
         println!(concat!(
             "done! run `",
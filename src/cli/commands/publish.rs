@@ -1,6 +1,8 @@
 use crate::cli::{display_err, run_on_workspace_members, up_to_date_lockfile};
 use anyhow::Context;
 use async_compression::Level;
+#[cfg(feature = "wally-compat")]
+use async_zip::{Compression, ZipEntryBuilder};
 use clap::Args;
 use colored::Colorize;
 use fs_err::tokio as fs;
@@ -20,9 +22,22 @@ use pesde::{
     },
     Project, DEFAULT_INDEX_NAME, MANIFEST_FILE_NAME,
 };
+#[cfg(feature = "wally-compat")]
+use pesde::{
+    manifest::{target::TargetKind, Manifest},
+    names::wally::WallyPackageName,
+    source::wally::{
+        compat_util::WALLY_MANIFEST_FILE_NAME, specifier::WallyDependencySpecifier,
+        WallyPackageSource,
+    },
+};
+#[cfg(feature = "wally-compat")]
+use relative_path::{RelativePath, RelativePathBuf};
 use reqwest::{header::AUTHORIZATION, StatusCode};
 use semver::VersionReq;
 use std::{collections::HashSet, path::PathBuf};
+#[cfg(feature = "wally-compat")]
+use std::{path::Path, str::FromStr};
 use tempfile::Builder;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
@@ -39,6 +54,11 @@ pub struct PublishCommand {
     /// The index to publish to
     #[arg(short, long, default_value_t = DEFAULT_INDEX_NAME.to_string())]
     index: String,
+
+    /// The Wally index to publish to, as a Wally package generated from the manifest
+    #[cfg(feature = "wally-compat")]
+    #[arg(long, conflicts_with = "index")]
+    wally: Option<String>,
 }
 
 impl PublishCommand {
@@ -270,6 +290,13 @@ info: otherwise, the file was deemed unnecessary, if you don't understand why, p
             }
         }
 
+        #[cfg(feature = "wally-compat")]
+        if let Some(index) = &self.wally {
+            return self
+                .publish_wally(project, &manifest, &paths, index, reqwest)
+                .await;
+        }
+
         for relative_path in &paths {
             let path = project.package_dir().join(relative_path);
 
@@ -588,6 +615,275 @@ info: otherwise, the file was deemed unnecessary, if you don't understand why, p
         Ok(())
     }
 
+    #[cfg(feature = "wally-compat")]
+    async fn publish_wally(
+        &self,
+        project: &Project,
+        manifest: &Manifest,
+        paths: &HashSet<PathBuf>,
+        index_name: &str,
+        reqwest: reqwest::Client,
+    ) -> anyhow::Result<()> {
+        let realm = match manifest.target.kind() {
+            TargetKind::Roblox => "shared",
+            TargetKind::RobloxServer => "server",
+            _ => anyhow::bail!("only roblox packages can be published to Wally"),
+        };
+
+        let index_url = manifest.wally_indices.get(index_name).context(format!(
+            "index {index_name} not found in wally_indices field"
+        ))?;
+        let source = WallyPackageSource::new(index_url.clone());
+        source
+            .refresh(project)
+            .await
+            .context("failed to refresh source")?;
+        let config = source
+            .config(project)
+            .await
+            .context("failed to get source config")?;
+
+        let (scope, name) = manifest.name.as_str();
+        let name = WallyPackageName::from_str(&format!(
+            "{}/{}",
+            scope.replace('_', "-"),
+            name.replace('_', "-")
+        ))
+        .context("package name is not a valid Wally package name")?;
+
+        let mut wally_manifest = toml_edit::DocumentMut::new();
+        let package = wally_manifest["package"].or_insert(toml_edit::table());
+        package["name"] = toml_edit::value(format!("{}/{}", name.as_str().0, name.as_str().1));
+        package["version"] = toml_edit::value(manifest.version.to_string());
+        package["registry"] = toml_edit::value(index_url.to_bstring().to_string());
+        package["realm"] = toml_edit::value(realm);
+        if let Some(description) = &manifest.description {
+            package["description"] = toml_edit::value(description);
+        }
+        if let Some(license) = &manifest.license {
+            package["license"] = toml_edit::value(license);
+        }
+        package["authors"] =
+            toml_edit::value(manifest.authors.iter().collect::<toml_edit::Array>());
+
+        for (alias, (specifier, ty)) in
+            manifest.all_dependencies().context("dependency conflict")?
+        {
+            let (dep_name, version) = match specifier {
+                DependencySpecifiers::Wally(specifier) => {
+                    let dep_index_name = specifier.index.as_deref().unwrap_or(DEFAULT_INDEX_NAME);
+                    if manifest.wally_indices.get(dep_index_name) != Some(index_url) {
+                        anyhow::bail!(
+                            "wally dependency {alias} is not from the {index_name} index"
+                        );
+                    }
+
+                    (specifier.name, specifier.version)
+                }
+                DependencySpecifiers::Pesde(specifier) => {
+                    let (scope, name) = specifier.name.as_str();
+                    let wally_specifier = WallyDependencySpecifier {
+                        name: WallyPackageName::from_str(&format!(
+                            "{}/{}",
+                            scope.replace('_', "-"),
+                            name.replace('_', "-")
+                        ))?,
+                        version: specifier.version.clone(),
+                        index: None,
+                    };
+
+                    match source
+                        .resolve(
+                            &wally_specifier,
+                            project,
+                            manifest.target.kind(),
+                            &mut HashSet::new(),
+                        )
+                        .await
+                    {
+                        Ok((_, versions)) if !versions.is_empty() => {}
+                        Ok(_) | Err(pesde::source::wally::errors::ResolveError::NotFound(_)) => {
+                            anyhow::bail!(
+                                "pesde dependency {alias} ({specifier}) has no Wally equivalent"
+                            )
+                        }
+                        Err(e) => {
+                            return Err(e).context(format!(
+                                "failed to look up {} on the Wally index",
+                                wally_specifier.name
+                            ))
+                        }
+                    }
+
+                    (wally_specifier.name, wally_specifier.version)
+                }
+                DependencySpecifiers::Git(_) | DependencySpecifiers::Workspace(_) => {
+                    anyhow::bail!("dependency {alias} cannot be published to Wally")
+                }
+            };
+
+            let dependency_key = match ty {
+                DependencyType::Dev => "dev-dependencies",
+                _ => "dependencies",
+            };
+            let (scope, name) = dep_name.as_str();
+
+            wally_manifest[dependency_key].or_insert(toml_edit::table())[&alias] =
+                toml_edit::value(format!("{scope}/{name}@{version}"));
+        }
+
+        let lib_path = manifest
+            .target
+            .lib_path()
+            .context("Wally packages must have a lib path")?;
+        // Wally packages are synced by Rojo, so the lib path needs to become the root instance
+        let root = match lib_path.parent() {
+            Some(parent)
+                if !parent.as_str().is_empty()
+                    && lib_path.file_stem().is_some_and(|stem| {
+                        stem == "init" || stem == "init.server" || stem == "init.client"
+                    }) =>
+            {
+                parent
+            }
+            _ => lib_path.as_relative_path(),
+        };
+        let mut tree = serde_json::Map::new();
+        tree.insert("$path".to_string(), root.as_str().into());
+        for build_file in manifest.target.build_files().into_iter().flatten() {
+            if root.starts_with(build_file) {
+                continue;
+            }
+
+            let build_file = RelativePath::new(build_file);
+            tree.insert(
+                build_file
+                    .file_stem()
+                    .unwrap_or(build_file.as_str())
+                    .to_string(),
+                serde_json::json!({ "$path": build_file.as_str() }),
+            );
+        }
+        let project_file = serde_json::json!({
+            "name": name.as_str().1,
+            "tree": tree,
+        });
+
+        let mut archive = async_zip::tokio::write::ZipFileWriter::with_tokio(vec![]);
+
+        for relative_path in paths {
+            let path = project.package_dir().join(relative_path);
+
+            if !path.is_file()
+                || [MANIFEST_FILE_NAME, WALLY_MANIFEST_FILE_NAME]
+                    .iter()
+                    .any(|file_name| relative_path == Path::new(file_name))
+            {
+                continue;
+            }
+
+            let contents = fs::read(&path)
+                .await
+                .context(format!("failed to read `{}`", relative_path.display()))?;
+            let file_name = RelativePathBuf::from_path(relative_path)
+                .context("failed to convert included path to a relative path")?;
+
+            archive
+                .write_entry_whole(
+                    ZipEntryBuilder::new(file_name.as_str().into(), Compression::Deflate),
+                    &contents,
+                )
+                .await
+                .context(format!("failed to write `{file_name}` to archive"))?;
+        }
+
+        for (file_name, contents) in [
+            (WALLY_MANIFEST_FILE_NAME, wally_manifest.to_string()),
+            (
+                "default.project.json",
+                serde_json::to_string_pretty(&project_file)
+                    .context("failed to serialize project file")?,
+            ),
+        ] {
+            archive
+                .write_entry_whole(
+                    ZipEntryBuilder::new(file_name.into(), Compression::Deflate),
+                    contents.as_bytes(),
+                )
+                .await
+                .context(format!("failed to write `{file_name}` to archive"))?;
+        }
+
+        let archive = archive
+            .close()
+            .await
+            .context("failed to finish archive")?
+            .into_inner();
+
+        {
+            println!("\n{}", "please confirm the following information:".bold());
+            println!("{WALLY_MANIFEST_FILE_NAME}:\n{wally_manifest}");
+            println!(
+                "default.project.json:\n{}",
+                serde_json::to_string_pretty(&project_file)?
+            );
+
+            if !self.dry_run
+                && !self.yes
+                && !inquire::Confirm::new("is this information correct?").prompt()?
+            {
+                println!("\n{}", "publish aborted".red().bold());
+
+                return Ok(());
+            }
+
+            println!();
+        }
+
+        if self.dry_run {
+            fs::write("package.zip", archive).await?;
+
+            println!(
+                "{}",
+                "(dry run) package written to package.zip".green().bold()
+            );
+
+            return Ok(());
+        }
+
+        let request = source.prepare_request(
+            project,
+            reqwest
+                .post(format!("{}/v1/publish", config.api()))
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                .body(archive),
+        );
+
+        let response = request.send().await.context("failed to send request")?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .context("failed to get response text")?;
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                println!(
+                    "{}",
+                    "unauthorized to publish under this scope".red().bold()
+                );
+            }
+            code if !code.is_success() => {
+                anyhow::bail!("failed to publish package: {code} ({text})");
+            }
+            _ => {
+                println!("{text}");
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn run(self, project: Project, reqwest: reqwest::Client) -> anyhow::Result<()> {
         let result = self.clone().run_impl(&project, reqwest.clone(), true).await;
         if project.workspace_dir().is_some() {
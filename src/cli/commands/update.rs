@@ -1,4 +1,4 @@
-use crate::cli::{progress_bar, repos::update_scripts, run_on_workspace_members};
+use crate::cli::{progress_bar, repos::update_scripts, run_on_workspace_members, DownloadedBytes};
 use anyhow::Context;
 use clap::Args;
 use colored::Colorize;
@@ -16,6 +16,12 @@ impl UpdateCommand {
         multi: MultiProgress,
         reqwest: reqwest::Client,
     ) -> anyhow::Result<()> {
+        let _lock = project.lock().await.context("failed to lock project")?;
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
         let mut refreshed_sources = HashSet::new();
 
         let manifest = project
@@ -45,8 +51,16 @@ impl UpdateCommand {
                 overrides: manifest.overrides,
 
                 graph: {
+                    let downloaded = Arc::new(DownloadedBytes::default());
                     let (rx, downloaded_graph) = project
-                        .download_graph(&graph, &mut refreshed_sources, &reqwest, false, false)
+                        .download_graph(
+                            &graph,
+                            &mut refreshed_sources,
+                            &reqwest,
+                            false,
+                            false,
+                            downloaded.clone(),
+                        )
                         .await
                         .context("failed to download dependencies")?;
 
@@ -57,6 +71,7 @@ impl UpdateCommand {
                         "📥 ".to_string(),
                         "downloading dependencies".to_string(),
                         "downloaded dependencies".to_string(),
+                        Some(downloaded),
                     )
                     .await?;
 
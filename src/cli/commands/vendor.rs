@@ -0,0 +1,49 @@
+use crate::cli::up_to_date_lockfile;
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use pesde::{
+    vendor::{VENDOR_CONFIG_FILE, VENDOR_FOLDER},
+    Project,
+};
+
+#[derive(Debug, Args)]
+pub struct VendorCommand {}
+
+impl VendorCommand {
+    pub async fn run(self, project: Project, reqwest: reqwest::Client) -> anyhow::Result<()> {
+        let _lock = project.lock().await.context("failed to lock project")?;
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
+        let graph = if let Some(lockfile) = up_to_date_lockfile(&project).await? {
+            lockfile.graph
+        } else {
+            anyhow::bail!("outdated lockfile, please run the install command first")
+        };
+
+        let config = project
+            .vendor(&graph, &reqwest)
+            .await
+            .context("failed to vendor dependencies")?;
+
+        println!(
+            "{}",
+            format!(
+                "vendored {} packages into {VENDOR_FOLDER}",
+                config
+                    .values()
+                    .map(|versions| versions.len())
+                    .sum::<usize>()
+            )
+            .green()
+        );
+        println!(
+            "commit the {VENDOR_FOLDER} folder, installs will then use it instead of downloading packages. remove {VENDOR_FOLDER}/{VENDOR_CONFIG_FILE} to stop"
+        );
+
+        Ok(())
+    }
+}
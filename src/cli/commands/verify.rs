@@ -0,0 +1,222 @@
+use crate::cli::up_to_date_lockfile;
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use futures::future::try_join_all;
+use pesde::{
+    refresh_sources,
+    source::{
+        refs::PackageRefs,
+        traits::{PackageRef, PackageSource},
+    },
+    verify::FileDifference,
+    Project,
+};
+use std::{collections::HashSet, sync::Arc};
+
+#[derive(Debug, Args)]
+pub struct VerifyCommand {
+    /// Whether to restore the differing packages to their original contents
+    #[arg(short, long)]
+    restore: bool,
+
+    /// Whether to turn the differences into patches
+    #[cfg(feature = "patches")]
+    #[arg(short, long, conflicts_with = "restore")]
+    patch: bool,
+}
+
+impl VerifyCommand {
+    pub async fn run(self, project: Project, reqwest: reqwest::Client) -> anyhow::Result<()> {
+        let _lock = project.lock().await.context("failed to lock project")?;
+        let _cas_lock = project
+            .lock_cas(false)
+            .await
+            .context("failed to lock CAS")?;
+
+        let graph = if let Some(lockfile) = up_to_date_lockfile(&project).await? {
+            lockfile.graph
+        } else {
+            anyhow::bail!("outdated lockfile, please run the install command first")
+        };
+
+        let manifest = project
+            .deser_manifest()
+            .await
+            .context("failed to read manifest")?;
+
+        refresh_sources(
+            &project,
+            graph
+                .values()
+                .flat_map(|versions| versions.values())
+                .map(|node| node.node.pkg_ref.source()),
+            &mut HashSet::new(),
+        )
+        .await?;
+
+        let diffs = try_join_all(
+            graph
+                .iter()
+                .flat_map(|(name, versions)| {
+                    versions
+                        .iter()
+                        .map(move |(version_id, node)| (name, version_id, node))
+                })
+                .filter(|(_, _, node)| !matches!(node.node.pkg_ref, PackageRefs::Workspace(_)))
+                .map(|(name, version_id, node)| {
+                    let project = project.clone();
+                    let reqwest = reqwest.clone();
+                    let manifest = &manifest;
+
+                    async move {
+                        let (package_fs, _) = node
+                            .node
+                            .pkg_ref
+                            .source()
+                            .download(&node.node.pkg_ref, &project, &reqwest, Arc::new(()))
+                            .await
+                            .context(format!("failed to download {name}@{version_id}"))?;
+
+                        let diff = project
+                            .diff_installed_package(manifest, name, version_id, node, &package_fs)
+                            .await
+                            .context(format!("failed to verify {name}@{version_id}"))?;
+
+                        Ok::<_, anyhow::Error>((name, version_id, package_fs, diff))
+                    }
+                }),
+        )
+        .await?
+        .into_iter()
+        .filter(|(_, _, _, diff)| !diff.is_empty())
+        .collect::<Vec<_>>();
+
+        if diffs.is_empty() {
+            println!(
+                "{}",
+                "all installed packages match their original contents".green()
+            );
+            return Ok(());
+        }
+
+        for (name, version_id, _, diff) in &diffs {
+            println!("{}", format!("{name}@{version_id}").bold());
+
+            for (path, difference) in &diff.files {
+                let difference_str = difference.to_string();
+                println!(
+                    "  {} {path}",
+                    match difference {
+                        FileDifference::Modified => difference_str.yellow(),
+                        FileDifference::ModifiedInCas => difference_str.red(),
+                        FileDifference::Missing => difference_str.red(),
+                        FileDifference::Extra => difference_str.cyan(),
+                    }
+                );
+            }
+        }
+
+        println!();
+
+        let original_lost = diffs.iter().any(|(_, _, _, diff)| diff.original_lost());
+
+        #[cfg(feature = "patches")]
+        if self.patch {
+            if original_lost {
+                anyhow::bail!(concat!(
+                    "the original contents of some files were overwritten in the CAS, so they can't be turned into patches. run `",
+                    env!("CARGO_BIN_NAME"),
+                    " cas verify --repair`, then reinstall"
+                ));
+            }
+
+            // check every package first, so that no patches are created if any of them fails
+            let patched = diffs
+                .iter()
+                .filter(|(name, version_id, _, _)| {
+                    manifest
+                        .patches
+                        .get(*name)
+                        .is_some_and(|versions| versions.contains_key(*version_id))
+                })
+                .map(|(name, version_id, _, _)| format!("{name}@{version_id}"))
+                .collect::<Vec<_>>();
+
+            if !patched.is_empty() {
+                anyhow::bail!(
+                    "these packages already have a patch, use `{} patch` to change it: {}",
+                    env!("CARGO_BIN_NAME"),
+                    patched.join(", ")
+                );
+            }
+
+            for (name, version_id, package_fs, diff) in &diffs {
+                let directory = project
+                    .data_dir()
+                    .join("patches")
+                    .join(name.escaped())
+                    .join(version_id.escaped())
+                    .join(chrono::Utc::now().timestamp().to_string());
+                fs_err::tokio::create_dir_all(&directory).await?;
+
+                package_fs
+                    .write_to(
+                        &directory,
+                        project.cas_dir(),
+                        pesde::source::fs::LinkMode::Copy,
+                    )
+                    .await
+                    .context("failed to write package contents")?;
+
+                pesde::patches::setup_patches_repo(&directory)?;
+
+                diff.apply_to(&directory)
+                    .await
+                    .context("failed to apply differences")?;
+
+                super::patch_commit::commit_patch(&project, name, version_id, &directory).await?;
+
+                println!("created patch for {name}@{version_id}");
+            }
+
+            println!(concat!(
+                "done! run `",
+                env!("CARGO_BIN_NAME"),
+                " install` to apply the patches"
+            ));
+
+            return Ok(());
+        }
+
+        if self.restore {
+            for (name, version_id, package_fs, diff) in &diffs {
+                diff.restore(package_fs, project.cas_dir(), project.link_mode())
+                    .await
+                    .context(format!("failed to restore {name}@{version_id}"))?;
+
+                println!("restored {name}@{version_id}");
+            }
+
+            return Ok(());
+        }
+
+        if original_lost {
+            println!(
+                "{}: the original contents of files modified in the CAS are lost, run `{} cas verify --repair`, then reinstall",
+                "note".blue(),
+                env!("CARGO_BIN_NAME")
+            );
+        }
+
+        anyhow::bail!(
+            "{} packages differ from their original contents, use --restore to undo the changes{}",
+            diffs.len(),
+            if cfg!(feature = "patches") {
+                " or --patch to keep them"
+            } else {
+                ""
+            }
+        );
+    }
+}
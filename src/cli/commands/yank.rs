@@ -0,0 +1,103 @@
+use crate::cli::{get_index, VersionedPackageName};
+use anyhow::Context;
+use clap::Args;
+use colored::Colorize;
+use pesde::{
+    manifest::target::TargetKind,
+    names::PackageName,
+    source::{pesde::PesdePackageSource, traits::PackageSource},
+    Project,
+};
+use reqwest::{header::AUTHORIZATION, Method, StatusCode};
+use semver::Version;
+
+#[derive(Debug, Args)]
+pub struct YankCommand {
+    /// The package version to yank
+    #[arg(index = 1)]
+    package: VersionedPackageName<Version, PackageName>,
+
+    /// The target of the package version to yank. Yanks every target if not specified
+    #[arg(short, long)]
+    target: Option<TargetKind>,
+
+    /// The reason for yanking the package version
+    #[arg(short, long)]
+    reason: Option<String>,
+
+    /// Whether to unyank the package version instead
+    #[arg(short, long, conflicts_with = "reason")]
+    undo: bool,
+
+    /// The index to yank the package version from. Defaults to `default`, or the configured default index if current directory doesn't have a manifest
+    #[arg(short, long)]
+    index: Option<String>,
+}
+
+impl YankCommand {
+    pub async fn run(self, project: Project, reqwest: reqwest::Client) -> anyhow::Result<()> {
+        let VersionedPackageName(name, Some(version)) = self.package else {
+            anyhow::bail!("a version to yank must be specified");
+        };
+
+        let index_url = get_index(&project, self.index.as_deref()).await?;
+
+        let source = PesdePackageSource::new(index_url.clone());
+        source
+            .refresh(&project)
+            .await
+            .context("failed to refresh source")?;
+        let config = source
+            .config(&project)
+            .await
+            .context("failed to get source config")?;
+
+        let mut request = reqwest.request(
+            if self.undo {
+                Method::DELETE
+            } else {
+                Method::PUT
+            },
+            format!(
+                "{}/v0/packages/{}/{version}/{}/yank",
+                config.api(),
+                name.to_string().replace("/", "%2F"),
+                self.target
+                    .map_or_else(|| "any".to_string(), |target| target.to_string()),
+            ),
+        );
+
+        if !self.undo {
+            request = request.json(&serde_json::json!({ "reason": self.reason }));
+        }
+
+        if let Some(token) = project.auth_config().tokens().get(&index_url) {
+            log::debug!("using token for {index_url}");
+            request = request.header(AUTHORIZATION, token);
+        }
+
+        let response = request.send().await.context("failed to send request")?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .context("failed to get response text")?;
+        match status {
+            StatusCode::NOT_FOUND => {
+                println!("{}", "package version not found".red().bold());
+            }
+            StatusCode::FORBIDDEN => {
+                println!("{}", "unauthorized to yank under this scope".red().bold());
+            }
+            code if !code.is_success() => {
+                anyhow::bail!("failed to yank package version: {code} ({text})");
+            }
+            _ => {
+                println!("{text}");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use crate::cli::{auth::Tokens, home_dir};
 use anyhow::Context;
 use fs_err::tokio as fs;
+use gix::sec::identity::Account;
+use pesde::{source::fs::LinkMode, GitCredentials};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CliConfig {
@@ -18,10 +21,73 @@ pub struct CliConfig {
 
     pub tokens: Tokens,
 
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mirrors: BTreeMap<String, Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_downloads: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_download_retries: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_mode: Option<LinkMode>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub git_credentials: BTreeMap<String, GitCredentialsConfig>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_checked_updates: Option<(chrono::DateTime<chrono::Utc>, semver::Version)>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GitCredentialsConfig {
+    Password {
+        username: String,
+        password: String,
+    },
+    Token {
+        token: String,
+    },
+    SshKey {
+        ssh_key: PathBuf,
+    },
+    SshAgent {
+        ssh_agent: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        agent_socket: Option<PathBuf>,
+    },
+}
+
+impl GitCredentialsConfig {
+    pub fn into_credentials(self) -> Option<GitCredentials> {
+        Some(match self {
+            GitCredentialsConfig::Password { username, password } => {
+                GitCredentials::Password(Account { username, password })
+            }
+            GitCredentialsConfig::Token { token } => GitCredentials::Token(token),
+            GitCredentialsConfig::SshKey { ssh_key } => GitCredentials::SshKey(ssh_key),
+            GitCredentialsConfig::SshAgent {
+                ssh_agent,
+                agent_socket,
+            } => {
+                if !ssh_agent {
+                    return None;
+                }
+
+                GitCredentials::SshAgent(agent_socket)
+            }
+        })
+    }
+}
+
 impl Default for CliConfig {
     fn default() -> Self {
         Self {
@@ -30,11 +96,47 @@ impl Default for CliConfig {
 
             tokens: Tokens(Default::default()),
 
+            mirrors: Default::default(),
+
+            max_concurrent_downloads: None,
+            max_download_retries: None,
+
+            proxy: None,
+            no_proxy: None,
+            ca_file: None,
+
+            link_mode: None,
+
+            git_credentials: Default::default(),
+
             last_checked_updates: None,
         }
     }
 }
 
+impl CliConfig {
+    /// The proxy to send requests through, `PESDE_PROXY` takes precedence over the config
+    pub fn proxy(&self) -> Option<String> {
+        std::env::var("PESDE_PROXY")
+            .ok()
+            .or_else(|| self.proxy.clone())
+    }
+
+    /// The hosts to not proxy, `PESDE_NO_PROXY` takes precedence over the config
+    pub fn no_proxy(&self) -> Option<String> {
+        std::env::var("PESDE_NO_PROXY")
+            .ok()
+            .or_else(|| self.no_proxy.clone())
+    }
+
+    /// A PEM bundle of extra trusted CA certificates, `PESDE_CA_FILE` takes precedence over the config
+    pub fn ca_file(&self) -> Option<PathBuf> {
+        std::env::var_os("PESDE_CA_FILE")
+            .map(PathBuf::from)
+            .or_else(|| self.ca_file.clone())
+    }
+}
+
 pub async fn read_config() -> anyhow::Result<CliConfig> {
     let config_string = match fs::read_to_string(home_dir()?.join("config.toml")).await {
         Ok(config_string) => config_string,
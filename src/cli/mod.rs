@@ -2,23 +2,34 @@ use anyhow::Context;
 use colored::Colorize;
 use fs_err::tokio as fs;
 use futures::StreamExt;
-use indicatif::MultiProgress;
+use indicatif::{HumanBytes, MultiProgress};
 use pesde::{
+    download::DownloadProgressReporter,
     lockfile::Lockfile,
     manifest::target::TargetKind,
     names::{PackageName, PackageNames},
-    source::{version_id::VersionId, workspace::specifier::VersionTypeOrReq},
-    Project,
+    source::{
+        git_index::{read_file, root_tree, GitBasedSource},
+        pesde::{IndexFile, PesdePackageSource},
+        refs::PackageRefs,
+        version_id::VersionId,
+        workspace::specifier::VersionTypeOrReq,
+    },
+    Project, DEFAULT_INDEX_NAME,
 };
 use relative_path::RelativePathBuf;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     future::Future,
     path::PathBuf,
     str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::Duration,
 };
-use tokio::pin;
+use tokio::{pin, task::spawn_blocking};
 
 pub mod auth;
 pub mod commands;
@@ -44,6 +55,34 @@ pub async fn bin_dir() -> anyhow::Result<PathBuf> {
     Ok(bin_dir)
 }
 
+/// Gets the URL of an index from its name in the manifest, a URL, or the configured default index
+pub async fn get_index(project: &Project, index: Option<&str>) -> anyhow::Result<gix::Url> {
+    let manifest = match project.deser_manifest().await {
+        Ok(manifest) => Some(manifest),
+        Err(pesde::errors::ManifestReadError::Io(e))
+            if e.kind() == std::io::ErrorKind::NotFound =>
+        {
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    match (index, manifest) {
+        (Some(index), manifest) => match gix::Url::try_from(index) {
+            Ok(url) => Ok(url),
+            Err(_) => manifest
+                .and_then(|manifest| manifest.indices.get(index).cloned())
+                .context(format!("index {index} not found in manifest")),
+        },
+        (None, Some(manifest)) => manifest
+            .indices
+            .get(DEFAULT_INDEX_NAME)
+            .cloned()
+            .context(format!("index {DEFAULT_INDEX_NAME} not found in manifest")),
+        (None, None) => Ok(config::read_config().await?.default_index),
+    }
+}
+
 pub async fn up_to_date_lockfile(project: &Project) -> anyhow::Result<Option<Lockfile>> {
     let manifest = project.deser_manifest().await?;
     let lockfile = match project.deser_lockfile().await {
@@ -193,6 +232,15 @@ pub fn parse_gix_url(s: &str) -> Result<gix::Url, gix::url::parse::Error> {
     s.try_into()
 }
 
+#[derive(Debug, Default)]
+pub struct DownloadedBytes(AtomicU64);
+
+impl DownloadProgressReporter for DownloadedBytes {
+    fn report_progress(&self, len: u64) {
+        self.0.fetch_add(len, Ordering::Relaxed);
+    }
+}
+
 pub async fn progress_bar<E: std::error::Error + Into<anyhow::Error>>(
     len: u64,
     mut rx: tokio::sync::mpsc::Receiver<Result<String, E>>,
@@ -200,6 +248,7 @@ pub async fn progress_bar<E: std::error::Error + Into<anyhow::Error>>(
     prefix: String,
     progress_msg: String,
     finish_msg: String,
+    downloaded: Option<Arc<DownloadedBytes>>,
 ) -> anyhow::Result<()> {
     let bar = multi.add(
         indicatif::ProgressBar::new(len)
@@ -209,26 +258,126 @@ pub async fn progress_bar<E: std::error::Error + Into<anyhow::Error>>(
                     .progress_chars("█▓▒░ "),
             )
             .with_prefix(prefix)
-            .with_message(progress_msg),
+            .with_message(progress_msg.clone()),
     );
     bar.enable_steady_tick(Duration::from_millis(100));
 
-    while let Some(result) = rx.recv().await {
-        bar.inc(1);
+    let with_bytes = |text: &str| match &downloaded {
+        Some(downloaded) => format!(
+            "{text} ({})",
+            HumanBytes(downloaded.0.load(Ordering::Relaxed))
+        ),
+        None => text.to_string(),
+    };
+
+    let mut text = progress_msg;
+    let mut interval = tokio::time::interval(Duration::from_millis(100));
 
-        match result {
-            Ok(text) => {
-                bar.set_message(text);
+    loop {
+        tokio::select! {
+            result = rx.recv() => {
+                let Some(result) = result else {
+                    break;
+                };
+
+                bar.inc(1);
+
+                match result {
+                    Ok(t) => text = t,
+                    Err(e) => return Err(e.into()),
+                }
+
+                bar.set_message(with_bytes(&text));
+            }
+            _ = interval.tick(), if downloaded.is_some() => {
+                bar.set_message(with_bytes(&text));
             }
-            Err(e) => return Err(e.into()),
         }
     }
 
-    bar.finish_with_message(finish_msg);
+    bar.finish_with_message(with_bytes(&finish_msg));
 
     Ok(())
 }
 
+/// Warns about packages which have been yanked or deprecated in their index
+pub async fn warn_about_index_entries<'a>(
+    project: &Project,
+    packages: impl IntoIterator<Item = (&'a PackageNames, &'a VersionId, &'a PackageRefs)>,
+) -> anyhow::Result<()> {
+    // group the packages by index and name, so every index and index file is only read once
+    let mut indices = HashMap::<gix::Url, BTreeMap<PackageName, Vec<(String, VersionId)>>>::new();
+
+    for (name, version_id, pkg_ref) in packages {
+        let PackageRefs::Pesde(pkg_ref) = pkg_ref else {
+            continue;
+        };
+
+        indices
+            .entry(pkg_ref.index_url.clone())
+            .or_default()
+            .entry(pkg_ref.name.clone())
+            .or_default()
+            .push((format!("{name}@{version_id}"), version_id.clone()));
+    }
+
+    let indices = indices
+        .into_iter()
+        .map(|(index_url, packages)| {
+            let path = PesdePackageSource::new(index_url.clone()).path(project);
+            (index_url, path, packages)
+        })
+        .collect::<Vec<_>>();
+
+    spawn_blocking(move || {
+        for (index_url, path, packages) in indices {
+            // indices of vendored packages and packages in the CAS aren't fetched when installing
+            if !path.exists() {
+                log::debug!("index {index_url} isn't fetched, not checking its packages");
+                continue;
+            }
+
+            let repo = gix::open(&path).context("failed to open index")?;
+            let tree = root_tree(&repo).context("failed to get index tree")?;
+
+            for (name, versions) in packages {
+                let (scope, name_part) = name.as_str();
+                let Some(file) = read_file(&tree, [scope, name_part])
+                    .context("failed to read package index file")?
+                else {
+                    continue;
+                };
+                let mut entries: IndexFile =
+                    toml::from_str(&file).context("failed to parse package index file")?;
+
+                for (display_name, version_id) in versions {
+                    let Some(entry) = entries.remove(&version_id) else {
+                        continue;
+                    };
+
+                    if entry.yanked {
+                        log::warn!(
+                            "{display_name} is yanked{}",
+                            entry
+                                .yank_reason
+                                .map(|reason| format!(": {reason}"))
+                                .unwrap_or_default()
+                        );
+                    }
+
+                    if let Some(message) = entry.deprecated {
+                        log::warn!("{display_name} is deprecated: {message}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+    .await
+    .unwrap()
+}
+
 pub fn shift_project_dir(project: &Project, pkg_dir: PathBuf) -> Project {
     Project::new(
         pkg_dir,
@@ -237,6 +386,10 @@ pub fn shift_project_dir(project: &Project, pkg_dir: PathBuf) -> Project {
         project.cas_dir(),
         project.auth_config().clone(),
     )
+    .with_mirrors(project.mirrors().clone())
+    .with_download_config(project.download_config().clone())
+    .with_network_config(project.network_config().clone())
+    .with_link_mode(project.link_mode())
 }
 
 pub async fn run_on_workspace_members<F: Future<Output = anyhow::Result<()>>>(
@@ -1,6 +1,6 @@
 use crate::{
     cli::{config::read_config, home_dir},
-    util::authenticate_conn,
+    util::{authenticate_conn, git_config_overrides},
 };
 use anyhow::Context;
 use fs_err::tokio as fs;
@@ -16,10 +16,19 @@ async fn update_repo<P: AsRef<Path>>(
     project: &Project,
 ) -> anyhow::Result<()> {
     let path = path.as_ref();
+    let _lock = project
+        .lock_repo(path)
+        .await
+        .context(format!("failed to lock {name} repository"))?;
     let should_update = path.exists();
+    let config_overrides = git_config_overrides(&url, project);
 
     let (repo, oid) = if should_update {
-        let repo = gix::open(path).context(format!("failed to open {name} repository"))?;
+        let repo = gix::open_opts(
+            path,
+            gix::open::Options::default().config_overrides(config_overrides),
+        )
+        .context(format!("failed to open {name} repository"))?;
 
         let remote = repo
             .find_default_remote(Direction::Fetch)
@@ -61,6 +70,7 @@ async fn update_repo<P: AsRef<Path>>(
 
         let repo = gix::prepare_clone(url, path)
             .context(format!("failed to prepare {name} repository clone"))?
+            .with_in_memory_config_overrides(config_overrides)
             .with_shallow(Shallow::Deepen(1))
             .fetch_only(gix::progress::Discard, &false.into())
             .context(format!("failed to fetch and checkout {name} repository"))?
@@ -128,16 +138,33 @@ pub async fn update_scripts(project: &Project) -> anyhow::Result<()> {
     let home_dir = home_dir()?;
     let config = read_config().await?;
 
+    let scripts_dir = home_dir.join("scripts");
+    let existed = scripts_dir.exists();
+
     let project = project.clone();
-    spawn_blocking(move || {
-        Handle::current().block_on(update_repo(
-            "scripts",
-            home_dir.join("scripts"),
-            config.scripts_repo,
-            &project,
-        ))
+    let path = scripts_dir.clone();
+    let result = spawn_blocking(move || {
+        Handle::current().block_on(update_repo("scripts", path, config.scripts_repo, &project))
     })
-    .await??;
+    .await?;
+
+    // allows working offline, only the scripts which use the repository fail without it
+    match result {
+        Err(e) if existed => {
+            log::warn!("failed to update scripts repository, using the existing copy: {e:#}");
+        }
+        Err(e) => {
+            log::warn!("failed to fetch scripts repository, scripts using it won't work: {e:#}");
+
+            // a partial clone would be mistaken for an existing copy later
+            if let Err(e) = fs::remove_dir_all(&scripts_dir).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::debug!("failed to remove partial scripts repository: {e}");
+                }
+            }
+        }
+        Ok(()) => {}
+    }
 
     Ok(())
 }
@@ -1,18 +1,132 @@
 use crate::{
     lockfile::{DependencyGraph, DownloadedDependencyGraphNode, DownloadedGraph},
     manifest::DependencyType,
+    names::PackageNames,
     refresh_sources,
     source::{
+        refs::PackageRefs,
         traits::{PackageRef, PackageSource},
+        version_id::VersionId,
         PackageSources,
     },
+    vendor::{copy_dir, VendorConfig, VendoredPackage},
     Project, PACKAGES_CONTAINER_NAME,
 };
 use fs_err::tokio as fs;
+use futures::StreamExt;
+use reqwest::{header::RETRY_AFTER, StatusCode};
 use std::{
     collections::HashSet,
+    path::Path,
     sync::{Arc, Mutex},
+    time::Duration,
 };
+use tokio::{io::AsyncBufRead, sync::Semaphore};
+use tokio_util::io::StreamReader;
+
+/// Reports the progress of package downloads
+pub trait DownloadProgressReporter: Send + Sync {
+    /// Reports that `len` more bytes have been downloaded
+    fn report_progress(&self, _len: u64) {}
+}
+
+impl DownloadProgressReporter for () {}
+
+/// Configuration for downloading packages
+#[derive(Debug, Clone)]
+pub struct DownloadConfig {
+    max_concurrency: usize,
+    max_retries: u32,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 16,
+            max_retries: 3,
+        }
+    }
+}
+
+impl DownloadConfig {
+    /// Create a new `DownloadConfig`
+    pub fn new() -> Self {
+        DownloadConfig::default()
+    }
+
+    /// Set the maximum amount of packages downloaded at once
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Set the maximum amount of times a failed request is retried
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Get the maximum amount of packages downloaded at once
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// Get the maximum amount of times a failed request is retried
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+}
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Sends a request, retrying network errors, rate limits and server errors with exponential backoff.
+/// The `Retry-After` header is respected if present
+pub(crate) async fn send_with_retries(
+    request: reqwest::RequestBuilder,
+    max_retries: u32,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut attempt = 0;
+
+    loop {
+        // requests with streaming bodies can't be retried
+        let Some(this) = request.try_clone() else {
+            return request.send().await?.error_for_status();
+        };
+
+        let retry_after = match this.send().await {
+            Ok(response) => {
+                let status = response.status();
+                if attempt >= max_retries
+                    || !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+                {
+                    return response.error_for_status();
+                }
+
+                log::debug!("request to {} failed with {status}", response.url());
+
+                response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok())
+                    .map(Duration::from_secs)
+            }
+            Err(e) if attempt < max_retries && (e.is_connect() || e.is_timeout()) => {
+                log::debug!("request failed: {e}");
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        let delay = retry_after
+            .unwrap_or_else(|| Duration::from_millis(500 * 2u64.pow(attempt)))
+            .min(MAX_RETRY_DELAY);
+        attempt += 1;
+
+        log::debug!("retrying request in {delay:?} (attempt {attempt}/{max_retries})");
+        tokio::time::sleep(delay).await;
+    }
+}
 
 type MultithreadedGraph = Arc<Mutex<DownloadedGraph>>;
 
@@ -21,8 +135,37 @@ type MultithreadDownloadJob = (
     MultithreadedGraph,
 );
 
+/// Turns a response's body into a reader, calling `on_chunk` for every chunk received
+pub(crate) fn response_reader<F: FnMut(&[u8]) -> std::io::Result<()> + Send + 'static>(
+    response: reqwest::Response,
+    mut on_chunk: F,
+) -> impl AsyncBufRead + Unpin + Send {
+    StreamReader::new(Box::pin(response.bytes_stream().map(move |chunk| {
+        let chunk = chunk.map_err(std::io::Error::other)?;
+        on_chunk(&chunk)?;
+        Ok::<_, std::io::Error>(chunk)
+    })))
+}
+
+/// Finds the vendored copy of a package, if it was made from the same package reference and
+/// its folder still exists
+fn vendored<'a>(
+    vendor_config: &'a VendorConfig,
+    vendor_dir: &Path,
+    name: &PackageNames,
+    version_id: &VersionId,
+    pkg_ref: &PackageRefs,
+) -> Option<&'a VendoredPackage> {
+    vendor_config
+        .get(name)
+        .and_then(|versions| versions.get(version_id))
+        .filter(|vendored| vendored.pkg_ref == *pkg_ref)
+        .filter(|vendored| vendored.path.to_path(vendor_dir).is_dir())
+}
+
 impl Project {
-    /// Downloads a graph of dependencies
+    /// Downloads a graph of dependencies. Vendored packages are copied from the vendor folder
+    /// instead
     pub async fn download_graph(
         &self,
         graph: &DependencyGraph,
@@ -30,9 +173,33 @@ impl Project {
         reqwest: &reqwest::Client,
         prod: bool,
         write: bool,
+        reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<MultithreadDownloadJob, errors::DownloadGraphError> {
         let manifest = self.deser_manifest().await?;
         let manifest_target_kind = manifest.target.kind();
+        let vendor_config = Arc::new(self.deser_vendor_config().await?.unwrap_or_default());
+        let vendor_dir = self.vendor_dir();
+
+        for (name, versions) in graph {
+            for (version_id, node) in versions {
+                let Some(vendored) = vendor_config
+                    .get(name)
+                    .and_then(|versions| versions.get(version_id))
+                else {
+                    continue;
+                };
+
+                if vendored.pkg_ref != node.pkg_ref {
+                    log::warn!(
+                        "vendored copy of {name}@{version_id} doesn't match the lockfile, downloading it instead. run the vendor command to update it"
+                    );
+                } else if !vendored.path.to_path(&vendor_dir).is_dir() {
+                    log::warn!(
+                        "vendored copy of {name}@{version_id} is missing, downloading it instead. run the vendor command to restore it"
+                    );
+                }
+            }
+        }
         let downloaded_graph: MultithreadedGraph = Arc::new(Mutex::new(Default::default()));
 
         let (tx, rx) = tokio::sync::mpsc::channel(
@@ -43,16 +210,35 @@ impl Project {
                 .max(1),
         );
 
+        // vendored packages and packages already in the CAS don't need their sources, which lets
+        // installs work offline
         refresh_sources(
             self,
-            graph
-                .iter()
-                .flat_map(|(_, versions)| versions.iter())
-                .map(|(_, node)| node.pkg_ref.source()),
+            graph.iter().flat_map(|(name, versions)| {
+                versions
+                    .iter()
+                    .filter(|(version_id, node)| {
+                        vendored(&vendor_config, &vendor_dir, name, version_id, &node.pkg_ref)
+                            .is_none()
+                    })
+                    .filter(|(_, node)| {
+                        !node
+                            .pkg_ref
+                            .cas_index_path(self.cas_dir())
+                            .is_some_and(|index_file| index_file.exists())
+                    })
+                    .map(|(_, node)| node.pkg_ref.source())
+            }),
             refreshed_sources,
         )
         .await?;
 
+        if write {
+            self.track_cas_usage().await?;
+        }
+
+        let semaphore = Arc::new(Semaphore::new(self.download_config().max_concurrency()));
+
         for (name, versions) in graph {
             for (version_id, node) in versions {
                 let tx = tx.clone();
@@ -64,10 +250,14 @@ impl Project {
                 let project = Arc::new(self.clone());
                 let reqwest = reqwest.clone();
                 let downloaded_graph = downloaded_graph.clone();
+                let semaphore = semaphore.clone();
+                let reporter = reporter.clone();
+                let vendor_config = vendor_config.clone();
 
                 let package_dir = self.package_dir().to_path_buf();
 
                 tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.unwrap();
                     let source = node.pkg_ref.source();
 
                     let container_folder = node.container_folder(
@@ -90,27 +280,85 @@ impl Project {
 
                     let project = project.clone();
 
-                    log::debug!("downloading {name}@{version_id}");
+                    if let Some(vendored) = vendored(
+                        &vendor_config,
+                        &project.vendor_dir(),
+                        &name,
+                        &version_id,
+                        &node.pkg_ref,
+                    ) {
+                        log::debug!("using vendored {name}@{version_id}");
 
-                    let (fs, target) =
-                        match source.download(&node.pkg_ref, &project, &reqwest).await {
-                            Ok(target) => target,
-                            Err(e) => {
-                                tx.send(Err(Box::new(e).into())).await.unwrap();
+                        if write && (!prod || node.resolved_ty != DependencyType::Dev) {
+                            if let Err(e) = copy_dir(
+                                vendored.path.to_path(project.vendor_dir()),
+                                container_folder,
+                            )
+                            .await
+                            {
+                                tx.send(Err(errors::DownloadGraphError::WriteFailed(
+                                    format!("{name}@{version_id}"),
+                                    e,
+                                )))
+                                .await
+                                .unwrap();
                                 return;
                             }
-                        };
+                        }
+
+                        let display_name = format!("{name}@{version_id}");
+
+                        {
+                            let mut downloaded_graph = downloaded_graph.lock().unwrap();
+                            downloaded_graph.entry(name).or_default().insert(
+                                version_id,
+                                DownloadedDependencyGraphNode {
+                                    node,
+                                    target: vendored.target.clone(),
+                                },
+                            );
+                        }
+
+                        drop(downloaded_graph);
+
+                        tx.send(Ok(display_name)).await.unwrap();
+                        return;
+                    }
+
+                    log::debug!("downloading {name}@{version_id}");
+
+                    let (fs, target) = match source
+                        .download(&node.pkg_ref, &project, &reqwest, reporter)
+                        .await
+                    {
+                        Ok(target) => target,
+                        Err(e) => {
+                            tx.send(Err(errors::DownloadGraphError::DownloadFailed(
+                                format!("{name}@{version_id}"),
+                                Box::new(e),
+                            )))
+                            .await
+                            .unwrap();
+                            return;
+                        }
+                    };
 
                     log::debug!("downloaded {name}@{version_id}");
 
                     if write {
                         if !prod || node.resolved_ty != DependencyType::Dev {
-                            match fs.write_to(container_folder, project.cas_dir(), true).await {
+                            match fs
+                                .write_to(container_folder, project.cas_dir(), project.link_mode())
+                                .await
+                            {
                                 Ok(_) => {}
                                 Err(e) => {
-                                    tx.send(Err(errors::DownloadGraphError::WriteFailed(e)))
-                                        .await
-                                        .unwrap();
+                                    tx.send(Err(errors::DownloadGraphError::WriteFailed(
+                                        format!("{name}@{version_id}"),
+                                        e,
+                                    )))
+                                    .await
+                                    .unwrap();
                                     return;
                                 }
                             };
@@ -129,6 +377,9 @@ impl Project {
                             .insert(version_id, DownloadedDependencyGraphNode { node, target });
                     }
 
+                    // the graph must be released before the receiver can observe the last message
+                    drop(downloaded_graph);
+
                     tx.send(Ok(display_name)).await.unwrap();
                 });
             }
@@ -158,12 +409,16 @@ pub mod errors {
         #[error("error interacting with the filesystem")]
         Io(#[from] std::io::Error),
 
+        /// Error reading the vendor config
+        #[error("error reading vendor config")]
+        VendorConfigRead(#[from] crate::vendor::errors::VendorConfigReadError),
+
         /// Error downloading a package
-        #[error("failed to download package")]
-        DownloadFailed(#[from] Box<crate::source::errors::DownloadError>),
+        #[error("failed to download package {0}")]
+        DownloadFailed(String, #[source] Box<crate::source::errors::DownloadError>),
 
         /// Error writing package contents
-        #[error("failed to write package contents")]
-        WriteFailed(#[source] std::io::Error),
+        #[error("failed to write package contents of {0}")]
+        WriteFailed(String, #[source] std::io::Error),
     }
 }
@@ -0,0 +1,306 @@
+use crate::{
+    lockfile::{
+        DependencyGraph, DependencyGraphNode, DownloadedDependencyGraphNode, DownloadedGraph, Graph,
+    },
+    manifest::{target::Target, DependencyType, Manifest},
+    names::PackageNames,
+    source::{refs::PackageRefs, version_id::VersionId},
+    util::hash,
+    Project, PACKAGES_CONTAINER_NAME,
+};
+use fs_err::tokio as fs;
+use relative_path::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// A package installed into a project
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledPackage {
+    /// The folder the package is installed in, relative to the project
+    pub container_folder: RelativePathBuf,
+    /// A hash of what determines the contents of the container folder
+    pub hash: String,
+    /// The target of the package
+    pub target: Target,
+}
+
+/// What is installed into a project, so that installs only need to change what differs
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct InstallState {
+    /// The installed packages
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: Graph<InstalledPackage>,
+    /// The linker files written into the project, relative to it, with the hashes of their contents
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub linker_files: BTreeMap<RelativePathBuf, String>,
+    /// The aliases added to the project's `.luaurc` file, with the paths they point to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl InstallState {
+    /// Returns whether the package is installed
+    pub fn is_installed(&self, name: &PackageNames, version_id: &VersionId) -> bool {
+        self.packages
+            .get(name)
+            .is_some_and(|versions| versions.contains_key(version_id))
+    }
+}
+
+/// Removes the directories between the path and the root which are empty
+pub(crate) async fn remove_empty_parents(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The folder a package is installed in, relative to the project
+fn container_folder(
+    manifest: &Manifest,
+    name: &PackageNames,
+    version_id: &VersionId,
+    node: &DependencyGraphNode,
+) -> RelativePathBuf {
+    let folder = node.container_folder(
+        &Path::new(&manifest.target.kind().packages_folder(version_id.target()))
+            .join(PACKAGES_CONTAINER_NAME),
+        name,
+        version_id.version(),
+    );
+
+    RelativePathBuf::from_path(folder).unwrap()
+}
+
+impl Project {
+    fn install_state_path(&self) -> PathBuf {
+        let package_dir = self.package_dir().to_string_lossy().to_string();
+
+        self.data_dir().join("installs").join(hash(&package_dir))
+    }
+
+    /// Hashes what determines the contents of a package's container folder
+    #[cfg_attr(not(feature = "patches"), allow(unused_variables))]
+    async fn package_hash(
+        &self,
+        manifest: &Manifest,
+        name: &PackageNames,
+        version_id: &VersionId,
+        node: &DependencyGraphNode,
+    ) -> Result<String, errors::InstallStateError> {
+        let contents = toml::to_string(&node.pkg_ref)?;
+
+        #[cfg(feature = "patches")]
+        let contents = match manifest
+            .patches
+            .get(name)
+            .and_then(|versions| versions.get(version_id))
+        {
+            Some(patch_path) => {
+                contents + &fs::read_to_string(patch_path.to_path(self.package_dir())).await?
+            }
+            None => contents,
+        };
+
+        Ok(hash(contents))
+    }
+
+    /// Deserialize what is installed into the project, if it was installed before
+    pub async fn deser_install_state(
+        &self,
+    ) -> Result<Option<InstallState>, errors::InstallStateReadError> {
+        match fs::read_to_string(self.install_state_path()).await {
+            Ok(string) => Ok(Some(toml::from_str(&string)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write what is installed into the project
+    pub async fn write_install_state(
+        &self,
+        state: &InstallState,
+    ) -> Result<(), errors::InstallStateWriteError> {
+        let path = self.install_state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(path, toml::to_string(state)?).await?;
+        Ok(())
+    }
+
+    /// Describes the packages of the graph as installed into the project
+    pub async fn installed_packages(
+        &self,
+        graph: &DownloadedGraph,
+    ) -> Result<Graph<InstalledPackage>, errors::InstallStateError> {
+        let manifest = self.deser_manifest().await?;
+        let mut packages = Graph::new();
+
+        for (name, versions) in graph {
+            for (version_id, node) in versions {
+                packages.entry(name.clone()).or_default().insert(
+                    version_id.clone(),
+                    InstalledPackage {
+                        container_folder: container_folder(&manifest, name, version_id, &node.node),
+                        hash: self
+                            .package_hash(&manifest, name, version_id, &node.node)
+                            .await?,
+                        target: node.target.clone(),
+                    },
+                );
+            }
+        }
+
+        Ok(packages)
+    }
+
+    /// Removes the installed packages which differ from the ones in the graph or are no longer in
+    /// it, along with any leftovers of the packages which are going to be installed. Returns the
+    /// packages which are kept as they are, which are also the only ones left in the state
+    pub async fn remove_outdated_packages(
+        &self,
+        state: &mut InstallState,
+        graph: &DependencyGraph,
+        prod: bool,
+    ) -> Result<DownloadedGraph, errors::InstallStateError> {
+        let manifest = self.deser_manifest().await?;
+        let mut kept = DownloadedGraph::new();
+        let mut kept_packages = Graph::new();
+        let mut outdated = BTreeSet::new();
+
+        for (name, versions) in graph {
+            for (version_id, node) in versions {
+                if prod && node.resolved_ty == DependencyType::Dev {
+                    continue;
+                }
+
+                let folder = container_folder(&manifest, name, version_id, node);
+
+                let installed = state
+                    .packages
+                    .get(name)
+                    .and_then(|versions| versions.get(version_id))
+                    // workspace members are copied, so their contents can change without their
+                    // reference changing
+                    .filter(|_| !matches!(node.pkg_ref, PackageRefs::Workspace(_)))
+                    .filter(|installed| installed.container_folder == folder);
+
+                let installed = match installed {
+                    Some(installed)
+                        if installed.hash
+                            == self.package_hash(&manifest, name, version_id, node).await?
+                            && fs::metadata(folder.to_path(self.package_dir()))
+                                .await
+                                .is_ok() =>
+                    {
+                        installed
+                    }
+                    _ => {
+                        outdated.insert(folder);
+                        continue;
+                    }
+                };
+
+                kept.entry(name.clone()).or_default().insert(
+                    version_id.clone(),
+                    DownloadedDependencyGraphNode {
+                        target: installed.target.clone(),
+                        node: node.clone(),
+                    },
+                );
+                kept_packages
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(version_id.clone(), installed.clone());
+            }
+        }
+
+        outdated.extend(
+            state
+                .packages
+                .iter()
+                .flat_map(|(name, versions)| {
+                    versions
+                        .iter()
+                        .map(move |(version_id, installed)| (name, version_id, installed))
+                })
+                .filter(|(name, version_id, _)| {
+                    !kept
+                        .get(*name)
+                        .is_some_and(|versions| versions.contains_key(*version_id))
+                })
+                .map(|(_, _, installed)| installed.container_folder.clone()),
+        );
+
+        for folder in outdated {
+            let folder = folder.to_path(self.package_dir());
+
+            match fs::remove_dir_all(&folder).await {
+                Ok(()) => {
+                    log::debug!("removed {}", folder.display());
+                    remove_empty_parents(&folder, self.package_dir()).await;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        state.packages = kept_packages;
+
+        Ok(kept)
+    }
+}
+
+/// Errors that can occur when tracking what is installed into a project
+pub mod errors {
+    use thiserror::Error;
+
+    /// Errors that can occur when reading the install state
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum InstallStateReadError {
+        /// Error reading the install state
+        #[error("error reading install state")]
+        Io(#[from] std::io::Error),
+
+        /// Error deserializing the install state
+        #[error("error deserializing install state")]
+        Serde(#[from] toml::de::Error),
+    }
+
+    /// Errors that can occur when writing the install state
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum InstallStateWriteError {
+        /// Error writing the install state
+        #[error("error writing install state")]
+        Io(#[from] std::io::Error),
+
+        /// Error serializing the install state
+        #[error("error serializing install state")]
+        Serde(#[from] toml::ser::Error),
+    }
+
+    /// Errors that can occur when comparing installed packages to a graph
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum InstallStateError {
+        /// Error reading the manifest
+        #[error("error reading manifest")]
+        Manifest(#[from] crate::errors::ManifestReadError),
+
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error serializing a package reference
+        #[error("error serializing package reference")]
+        Serde(#[from] toml::ser::Error),
+    }
+}
@@ -4,9 +4,10 @@
 //! It has been designed with multiple targets in mind, namely Roblox, Lune, and Luau.
 
 use crate::{
+    download::DownloadConfig,
     lockfile::Lockfile,
     manifest::Manifest,
-    source::{traits::PackageSource, PackageSources},
+    source::{fs::LinkMode, traits::PackageSource, PackageSources},
 };
 use async_stream::stream;
 use fs_err::tokio as fs;
@@ -18,12 +19,20 @@ use std::{
 };
 use wax::Pattern;
 
+/// Security advisories
+pub mod advisories;
+/// Managing the CAS (content-addressable storage)
+pub mod cas;
 /// Downloading packages
 pub mod download;
+/// Tracking what is installed into projects
+pub mod install_state;
 /// Linking packages
 pub mod linking;
 /// Lockfile
 pub mod lockfile;
+/// Locking projects and the CAS against concurrent use
+pub mod locks;
 /// Manifest
 pub mod manifest;
 /// Package names
@@ -38,6 +47,10 @@ pub mod scripts;
 /// Package sources
 pub mod source;
 pub(crate) mod util;
+/// Vendoring packages into projects
+pub mod vendor;
+/// Verifying installed packages against their original contents
+pub mod verify;
 
 /// The name of the manifest file
 pub const MANIFEST_FILE_NAME: &str = "pesde.toml";
@@ -49,11 +62,26 @@ pub const DEFAULT_INDEX_NAME: &str = "default";
 pub const PACKAGES_CONTAINER_NAME: &str = ".pesde";
 pub(crate) const LINK_LIB_NO_FILE_FOUND: &str = "____pesde_no_export_file_found";
 
+/// Credentials used to authenticate with a Git remote
+#[derive(Debug, Clone)]
+pub enum GitCredentials {
+    /// A username and password
+    Password(Account),
+    /// An access token
+    Token(String),
+    /// The path to an SSH private key
+    SshKey(PathBuf),
+    /// The SSH agent, optionally listening on a specific socket
+    SshAgent(Option<PathBuf>),
+}
+
 /// Struct containing the authentication configuration
 #[derive(Debug, Default, Clone)]
 pub struct AuthConfig {
     tokens: HashMap<gix::Url, String>,
     git_credentials: Option<Account>,
+    index_hosts: HashSet<String>,
+    host_git_credentials: HashMap<String, GitCredentials>,
 }
 
 impl AuthConfig {
@@ -74,12 +102,35 @@ impl AuthConfig {
         self
     }
 
-    /// Set the git credentials
+    /// Set the git credentials, used for the hosts of indices which have no credentials set
+    /// for them specifically
     pub fn with_git_credentials(mut self, git_credentials: Option<Account>) -> Self {
         self.git_credentials = git_credentials;
         self
     }
 
+    /// Set the hosts of indices the git credentials are used for, in addition to the hosts of
+    /// the indices which have a token
+    pub fn with_index_hosts<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        mut self,
+        index_hosts: I,
+    ) -> Self {
+        self.index_hosts = index_hosts
+            .into_iter()
+            .map(|host| host.as_ref().to_string())
+            .collect();
+        self
+    }
+
+    /// Set the git credentials to use for specific hosts
+    pub fn with_host_git_credentials<I: IntoIterator<Item = (String, GitCredentials)>>(
+        mut self,
+        host_git_credentials: I,
+    ) -> Self {
+        self.host_git_credentials = host_git_credentials.into_iter().collect();
+        self
+    }
+
     /// Get the tokens
     pub fn tokens(&self) -> &HashMap<gix::Url, String> {
         &self.tokens
@@ -89,6 +140,77 @@ impl AuthConfig {
     pub fn git_credentials(&self) -> Option<&Account> {
         self.git_credentials.as_ref()
     }
+
+    /// Get the git credentials set for specific hosts
+    pub fn host_git_credentials(&self) -> &HashMap<String, GitCredentials> {
+        &self.host_git_credentials
+    }
+
+    /// Get the git credentials to use for a URL, preferring ones set for its host. The git
+    /// credentials are only used for the hosts of indices, so they aren't sent to other hosts
+    pub fn git_credentials_for(&self, url: &gix::Url) -> Option<GitCredentials> {
+        let host = url.host()?;
+
+        if let Some(credentials) = self.host_git_credentials.get(host) {
+            return Some(credentials.clone());
+        }
+
+        let is_index_host = self.index_hosts.contains(host)
+            || self.tokens.keys().any(|index| index.host() == Some(host));
+
+        self.git_credentials
+            .clone()
+            .filter(|_| is_index_host)
+            .map(GitCredentials::Password)
+    }
+}
+
+/// Struct containing the network configuration used for HTTP(S) requests
+#[derive(Debug, Default, Clone)]
+pub struct NetworkConfig {
+    proxy: Option<String>,
+    no_proxy: Option<String>,
+    ca_file: Option<PathBuf>,
+}
+
+impl NetworkConfig {
+    /// Create a new `NetworkConfig`
+    pub fn new() -> Self {
+        NetworkConfig::default()
+    }
+
+    /// Set the proxy to send requests through
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Set the comma-separated hosts which bypass the proxy
+    pub fn with_no_proxy(mut self, no_proxy: Option<String>) -> Self {
+        self.no_proxy = no_proxy;
+        self
+    }
+
+    /// Set the PEM bundle of extra trusted CA certificates
+    pub fn with_ca_file(mut self, ca_file: Option<PathBuf>) -> Self {
+        self.ca_file = ca_file;
+        self
+    }
+
+    /// Get the proxy to send requests through
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Get the comma-separated hosts which bypass the proxy
+    pub fn no_proxy(&self) -> Option<&str> {
+        self.no_proxy.as_deref()
+    }
+
+    /// Get the PEM bundle of extra trusted CA certificates
+    pub fn ca_file(&self) -> Option<&Path> {
+        self.ca_file.as_deref()
+    }
 }
 
 /// The main struct of the pesde library, representing a project
@@ -99,6 +221,10 @@ pub struct Project {
     data_dir: PathBuf,
     auth_config: AuthConfig,
     cas_dir: PathBuf,
+    mirrors: HashMap<gix::Url, Vec<String>>,
+    download_config: DownloadConfig,
+    network_config: NetworkConfig,
+    link_mode: LinkMode,
 }
 
 impl Project {
@@ -116,9 +242,40 @@ impl Project {
             data_dir: data_dir.as_ref().to_path_buf(),
             auth_config,
             cas_dir: cas_dir.as_ref().to_path_buf(),
+            mirrors: HashMap::new(),
+            download_config: DownloadConfig::default(),
+            network_config: NetworkConfig::default(),
+            link_mode: LinkMode::default(),
         }
     }
 
+    /// Set the download mirrors to use for indices, which are tried before the ones they advertise
+    pub fn with_mirrors<I: IntoIterator<Item = (gix::Url, Vec<String>)>>(
+        mut self,
+        mirrors: I,
+    ) -> Self {
+        self.mirrors = mirrors.into_iter().collect();
+        self
+    }
+
+    /// Set the download configuration
+    pub fn with_download_config(mut self, download_config: DownloadConfig) -> Self {
+        self.download_config = download_config;
+        self
+    }
+
+    /// Set the network configuration
+    pub fn with_network_config(mut self, network_config: NetworkConfig) -> Self {
+        self.network_config = network_config;
+        self
+    }
+
+    /// Set how package files are placed in the project from the CAS
+    pub fn with_link_mode(mut self, link_mode: LinkMode) -> Self {
+        self.link_mode = link_mode;
+        self
+    }
+
     /// The directory of the package
     pub fn package_dir(&self) -> &Path {
         &self.package_dir
@@ -144,6 +301,26 @@ impl Project {
         &self.cas_dir
     }
 
+    /// The download mirrors configured for indices
+    pub fn mirrors(&self) -> &HashMap<gix::Url, Vec<String>> {
+        &self.mirrors
+    }
+
+    /// The download configuration
+    pub fn download_config(&self) -> &DownloadConfig {
+        &self.download_config
+    }
+
+    /// The network configuration
+    pub fn network_config(&self) -> &NetworkConfig {
+        &self.network_config
+    }
+
+    /// How package files are placed in the project from the CAS
+    pub fn link_mode(&self) -> LinkMode {
+        self.link_mode
+    }
+
     /// Read the manifest file
     pub async fn read_manifest(&self) -> Result<String, errors::ManifestReadError> {
         let string = fs::read_to_string(self.package_dir.join(MANIFEST_FILE_NAME)).await?;
@@ -432,3 +609,80 @@ pub mod errors {
         BuildGlob(#[from] wax::BuildError),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> gix::Url {
+        url.try_into().unwrap()
+    }
+
+    fn account(username: &str) -> Account {
+        Account {
+            username: username.to_string(),
+            password: "password".to_string(),
+        }
+    }
+
+    fn username(credentials: Option<GitCredentials>) -> Option<String> {
+        match credentials {
+            Some(GitCredentials::Password(account)) => Some(account.username),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn prefers_host_git_credentials() {
+        let auth_config = AuthConfig::new()
+            .with_git_credentials(Some(account("global")))
+            .with_index_hosts(["github.com"])
+            .with_host_git_credentials([
+                (
+                    "github.com".to_string(),
+                    GitCredentials::Password(account("host")),
+                ),
+                (
+                    "gitlab.com".to_string(),
+                    GitCredentials::Token("token".to_string()),
+                ),
+            ]);
+
+        assert_eq!(
+            username(auth_config.git_credentials_for(&url("https://github.com/pesde-pkg/index"))),
+            Some("host".to_string())
+        );
+        assert!(matches!(
+            auth_config.git_credentials_for(&url("https://gitlab.com/acme/package")),
+            Some(GitCredentials::Token(token)) if token == "token"
+        ));
+    }
+
+    #[test]
+    fn uses_git_credentials_for_index_hosts_only() {
+        let auth_config = AuthConfig::new()
+            .with_git_credentials(Some(account("global")))
+            .with_index_hosts(["git.example.com"])
+            .with_tokens([(url("https://index.example.com/index"), "token")]);
+
+        assert_eq!(
+            username(auth_config.git_credentials_for(&url("https://git.example.com/index"))),
+            Some("global".to_string())
+        );
+        assert_eq!(
+            username(auth_config.git_credentials_for(&url("https://index.example.com/other"))),
+            Some("global".to_string())
+        );
+        assert!(auth_config
+            .git_credentials_for(&url("https://github.com/acme/package"))
+            .is_none());
+        assert!(auth_config
+            .git_credentials_for(&url("file:///tmp/index"))
+            .is_none());
+
+        assert!(AuthConfig::new()
+            .with_index_hosts(["git.example.com"])
+            .git_credentials_for(&url("https://git.example.com/index"))
+            .is_none());
+    }
+}
@@ -1,62 +1,450 @@
-use std::path::{Component, Path};
+use std::{
+    collections::HashSet,
+    path::{Component, Path},
+};
 
 use crate::manifest::{target::TargetKind, Manifest};
-use full_moon::{ast::luau::ExportedTypeDeclaration, visitors::Visitor};
-use relative_path::RelativePathBuf;
+use full_moon::{
+    ast::{
+        luau::{
+            ExportedTypeDeclaration, GenericDeclarationParameter, GenericParameterInfo,
+            TypeDeclaration, TypeInfo,
+        },
+        Ast, Call, Expression, FunctionArgs, Index, LastStmt, Prefix, Suffix, Var,
+    },
+    node::Node,
+    tokenizer::{TokenReference, TokenType},
+    visitors::{Visit, Visitor},
+};
+use relative_path::{RelativePath, RelativePathBuf};
 
+/// The name of a generic parameter, including the ellipsis of variadic packs
+fn generic_parameter_name(generic: &GenericDeclarationParameter) -> String {
+    match generic.parameter() {
+        GenericParameterInfo::Name(name) => name.token().to_string(),
+        GenericParameterInfo::Variadic { name, .. } => format!("{}...", name.token()),
+        parameter => parameter.to_string().trim().to_string(),
+    }
+}
+
+/// The name a generic parameter can be referenced by inside types
+fn generic_parameter_base_name(generic: &GenericDeclarationParameter) -> String {
+    match generic.parameter() {
+        GenericParameterInfo::Variadic { name, .. } => name.token().to_string(),
+        _ => generic_parameter_name(generic),
+    }
+}
+
+#[derive(Default)]
 struct TypeVisitor {
-    types: Vec<String>,
+    exported: Vec<TypeDeclaration>,
+    exported_names: HashSet<String>,
+    declared_names: HashSet<String>,
 }
 
 impl Visitor for TypeVisitor {
     fn visit_exported_type_declaration(&mut self, node: &ExportedTypeDeclaration) {
-        let name = node.type_declaration().type_name().to_string();
+        self.exported_names
+            .insert(node.type_declaration().type_name().token().to_string());
+        self.exported.push(node.type_declaration().clone());
+    }
+
+    fn visit_type_declaration(&mut self, node: &TypeDeclaration) {
+        self.declared_names
+            .insert(node.type_name().token().to_string());
+    }
+}
 
-        let (declaration_generics, generics) =
-            if let Some(declaration) = node.type_declaration().generics() {
-                let mut declaration_generics = vec![];
-                let mut generics = vec![];
+/// Finds the references of a type to the types declared in its file
+struct TypeReferenceVisitor<'a> {
+    types: &'a TypeVisitor,
+    shadowed: HashSet<String>,
+    exported_references: Vec<usize>,
+    resolvable: bool,
+}
+
+impl Visitor for TypeReferenceVisitor<'_> {
+    fn visit_type_info(&mut self, node: &TypeInfo) {
+        let name = match node {
+            TypeInfo::Basic(name) | TypeInfo::Generic { base: name, .. } => name,
+            TypeInfo::Callback {
+                generics: Some(generics),
+                ..
+            } => {
+                self.shadowed
+                    .extend(generics.generics().iter().map(generic_parameter_base_name));
+                return;
+            }
+            // the linking module can't reference the file's locals
+            TypeInfo::Module { .. } | TypeInfo::Typeof { .. } => {
+                self.resolvable = false;
+                return;
+            }
+            _ => return,
+        };
+
+        let name_str = name.token().to_string();
+        if self.shadowed.contains(&name_str) {
+            return;
+        }
+
+        if self.types.exported_names.contains(&name_str) {
+            self.exported_references
+                .push(name.token().start_position().bytes());
+        } else if self.types.declared_names.contains(&name_str) {
+            self.resolvable = false;
+        }
+    }
+}
+
+impl TypeVisitor {
+    /// Renders a type as it can be written in a linking module, with the references to the types
+    /// exported by the file going through the module. None if it references the file's locals
+    fn qualify_type(
+        &self,
+        file: &str,
+        module: &str,
+        type_info: &TypeInfo,
+        parameters: &HashSet<String>,
+    ) -> Option<String> {
+        // the range of some types doesn't include their closing brackets, but their tokens do
+        let start = type_info
+            .tokens()
+            .map(|token| token.token().start_position().bytes())
+            .min()?;
+        let end = type_info
+            .tokens()
+            .map(|token| token.token().end_position().bytes())
+            .max()?;
+
+        let mut visitor = TypeReferenceVisitor {
+            types: self,
+            shadowed: parameters.clone(),
+            exported_references: vec![],
+            resolvable: true,
+        };
+        type_info.visit(&mut visitor);
+        if !visitor.resolvable {
+            return None;
+        }
 
-                for generic in declaration.generics().iter() {
-                    declaration_generics.push(generic.to_string());
+        visitor.exported_references.sort_unstable();
 
-                    if generic.default_type().is_some() {
-                        generics.push(generic.parameter().to_string())
-                    } else {
-                        generics.push(generic.to_string())
+        let mut rendered = String::new();
+        let mut last = start;
+        for reference in visitor.exported_references {
+            rendered.push_str(file.get(last..reference)?);
+            rendered.push_str(module);
+            rendered.push('.');
+            last = reference;
+        }
+        rendered.push_str(file.get(last..end)?);
+
+        Some(rendered)
+    }
+
+    /// Re-declares an exported type as the type of the module
+    fn redeclare(&self, file: &str, module: &str, declaration: &TypeDeclaration) -> String {
+        let name = declaration.type_name().token().to_string();
+
+        let Some(generics) = declaration.generics() else {
+            return format!("export type {name} = {module}.{name}\n");
+        };
+
+        let generics = generics.generics().iter().collect::<Vec<_>>();
+        let names = generics
+            .iter()
+            .map(|generic| generic_parameter_name(generic))
+            .collect::<Vec<_>>();
+        let parameters = generics
+            .iter()
+            .map(|generic| generic_parameter_base_name(generic))
+            .collect::<HashSet<_>>();
+
+        let mut defaults = generics
+            .iter()
+            .map(|generic| {
+                generic.default_type().map(|default| {
+                    self.qualify_type(file, module, default, &parameters)
+                        .ok_or(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // parameters with defaults must come after the ones without, so the defaults which can't be
+        // re-declared take the ones before them with them
+        if let Some(last_unresolvable) = defaults
+            .iter()
+            .rposition(|default| matches!(default, Some(Err(()))))
+        {
+            log::debug!(
+                "can't re-declare the defaults of type {name}'s generics, leaving them out"
+            );
+
+            for default in &mut defaults[..=last_unresolvable] {
+                *default = None;
+            }
+        }
+
+        let declaration_generics = names
+            .iter()
+            .zip(defaults)
+            .map(|(name, default)| match default {
+                Some(Ok(default)) => format!("{name} = {default}"),
+                _ => name.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "export type {name}<{}> = {module}.{name}<{}>\n",
+            declaration_generics.join(", "),
+            names.join(", ")
+        )
+    }
+}
+
+/// A module a file requires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequirePath {
+    /// Relative to the directory the file is in, as in `require("./foo")`
+    File(RelativePathBuf),
+    /// Relative to the instance of the file, as in `require(script.Parent.foo)` or
+    /// `require("@self/foo")`
+    Instance(RelativePathBuf),
+}
+
+impl RequirePath {
+    fn from_string(token: &TokenReference) -> Option<Self> {
+        let TokenType::StringLiteral { literal, .. } = token.token_type() else {
+            return None;
+        };
+
+        if literal.starts_with("./") || literal.starts_with("../") {
+            Some(RequirePath::File(RelativePathBuf::from(
+                literal.to_string(),
+            )))
+        } else if literal.as_str() == "@self" {
+            Some(RequirePath::Instance(RelativePathBuf::new()))
+        } else {
+            literal
+                .strip_prefix("@self/")
+                .map(|path| RequirePath::Instance(RelativePathBuf::from(path)))
+        }
+    }
+
+    fn from_instance<'a>(
+        prefix: &Prefix,
+        suffixes: impl Iterator<Item = &'a Suffix>,
+    ) -> Option<Self> {
+        match prefix {
+            Prefix::Name(name) if name.token().to_string() == "script" => {}
+            _ => return None,
+        }
+
+        let mut path = RelativePathBuf::new();
+        for suffix in suffixes {
+            let child = match suffix {
+                Suffix::Index(Index::Dot { name, .. }) => {
+                    let name = name.token().to_string();
+                    if name == "Parent" {
+                        path.push("..");
+                        continue;
                     }
-                }
 
-                (
-                    format!("<{}>", declaration_generics.join(", ")),
-                    format!("<{}>", generics.join(", ")),
-                )
-            } else {
-                ("".to_string(), "".to_string())
+                    name
+                }
+                Suffix::Index(Index::Brackets {
+                    expression: Expression::String(name),
+                    ..
+                }) => string_literal(name)?,
+                Suffix::Call(Call::MethodCall(call))
+                    if matches!(
+                        call.name().token().to_string().as_str(),
+                        "FindFirstChild" | "WaitForChild"
+                    ) =>
+                {
+                    match call.args() {
+                        FunctionArgs::String(name) => string_literal(name)?,
+                        FunctionArgs::Parentheses { arguments, .. } => {
+                            match arguments.iter().next()? {
+                                Expression::String(name) => string_literal(name)?,
+                                _ => return None,
+                            }
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return None,
             };
 
-        self.types.push(format!(
-            "export type {name}{declaration_generics} = module.{name}{generics}\n"
-        ));
+            path.push(child);
+        }
+
+        Some(RequirePath::Instance(path))
+    }
+
+    fn from_expression(expression: &Expression) -> Option<Self> {
+        let call = match expression {
+            Expression::FunctionCall(call) => call,
+            Expression::Parentheses { expression, .. }
+            | Expression::TypeAssertion { expression, .. } => {
+                return Self::from_expression(expression)
+            }
+            _ => return None,
+        };
+
+        match call.prefix() {
+            Prefix::Name(name) if name.token().to_string() == "require" => {}
+            _ => return None,
+        }
+
+        let mut suffixes = call.suffixes();
+        let (Some(Suffix::Call(Call::AnonymousCall(args))), None) =
+            (suffixes.next(), suffixes.next())
+        else {
+            return None;
+        };
+
+        match args {
+            FunctionArgs::String(path) => Self::from_string(path),
+            FunctionArgs::Parentheses { arguments, .. } if arguments.len() == 1 => {
+                match arguments.iter().next()? {
+                    Expression::String(path) => Self::from_string(path),
+                    Expression::Var(Var::Expression(var)) => {
+                        Self::from_instance(var.prefix(), var.suffixes())
+                    }
+                    Expression::FunctionCall(call) => {
+                        Self::from_instance(call.prefix(), call.suffixes())
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The paths of the files this may refer to when required from the file, in order of
+    /// precedence. Empty if it refers to something outside the file's package
+    pub fn candidates(&self, file: &RelativePath) -> Vec<RelativePathBuf> {
+        let base = match self {
+            RequirePath::File(path) => file
+                .parent()
+                .unwrap_or(RelativePath::new(""))
+                .join_normalized(path),
+            RequirePath::Instance(path) => {
+                // the children of a module are in the folder of its `init` file
+                let instance = match file.file_stem() {
+                    Some("init") => file
+                        .parent()
+                        .unwrap_or(RelativePath::new(""))
+                        .to_relative_path_buf(),
+                    _ => file.with_extension(""),
+                };
+
+                instance.join_normalized(path)
+            }
+        };
+
+        if base.as_str().is_empty()
+            || base
+                .components()
+                .next()
+                .is_some_and(|component| component == relative_path::Component::ParentDir)
+        {
+            return vec![];
+        }
+
+        let mut candidates = vec![];
+        if matches!(base.extension(), Some("lua" | "luau")) {
+            candidates.push(base.clone());
+        }
+        for extension in ["luau", "lua"] {
+            candidates.push(RelativePathBuf::from(format!("{base}.{extension}")));
+        }
+        for extension in ["luau", "lua"] {
+            candidates.push(base.join(format!("init.{extension}")));
+        }
+
+        candidates
     }
 }
 
-/// Get the types exported by a file
-pub fn get_file_types(file: &str) -> Result<Vec<String>, Vec<full_moon::Error>> {
+fn string_literal(token: &TokenReference) -> Option<String> {
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+        _ => None,
+    }
+}
+
+/// The module a file returns, if it returns one it requires
+fn returned_require(ast: &Ast) -> Option<RequirePath> {
+    let LastStmt::Return(statement) = ast.nodes().last_stmt()? else {
+        return None;
+    };
+
+    let mut returns = statement.returns().iter();
+    let (Some(expression), None) = (returns.next(), returns.next()) else {
+        return None;
+    };
+
+    RequirePath::from_expression(expression)
+}
+
+/// The types exported by a file
+#[derive(Debug, Default)]
+pub struct FileTypes {
+    /// The names of the exported types, with their re-declarations as the types of the module
+    pub types: Vec<(String, String)>,
+    /// The module the file returns, if it returns one it requires. The types it exports are
+    /// also public types of the file
+    pub returned_require: Option<RequirePath>,
+}
+
+/// The name of the variable a linking module stores a module in, for types to be re-declared as
+/// its types. The module at index 0 is the library itself
+pub fn module_variable(index: usize) -> String {
+    match index {
+        0 => "module".to_string(),
+        index => format!("module_{index}"),
+    }
+}
+
+/// Get the types exported by a file, re-declared as the types of the module in the variable
+pub fn get_file_types(file: &str, module: &str) -> Result<FileTypes, Vec<full_moon::Error>> {
     let ast = full_moon::parse(file)?;
-    let mut visitor = TypeVisitor { types: vec![] };
+    let mut visitor = TypeVisitor::default();
     visitor.visit_ast(&ast);
 
-    Ok(visitor.types)
+    Ok(FileTypes {
+        types: visitor
+            .exported
+            .iter()
+            .map(|declaration| {
+                (
+                    declaration.type_name().token().to_string(),
+                    visitor.redeclare(file, module, declaration),
+                )
+            })
+            .collect(),
+        returned_require: returned_require(&ast),
+    })
 }
 
 /// Generate a linking module for a library
 pub fn generate_lib_linking_module<I: IntoIterator<Item = S>, S: AsRef<str>>(
     path: &str,
+    type_module_paths: &[String],
     types: I,
 ) -> String {
     let mut output = format!("local module = require({path})\n");
 
+    for (index, type_module_path) in type_module_paths.iter().enumerate() {
+        output.push_str(&format!(
+            "local {} = require({type_module_path})\n",
+            module_variable(index + 1)
+        ));
+    }
+
     for ty in types {
         output.push_str(ty.as_ref());
     }
@@ -66,7 +454,7 @@ pub fn generate_lib_linking_module<I: IntoIterator<Item = S>, S: AsRef<str>>(
     output
 }
 
-fn luau_style_path(path: &Path) -> String {
+fn luau_path(path: &Path) -> String {
     let path = path
         .components()
         .filter_map(|ct| match ct {
@@ -78,8 +466,11 @@ fn luau_style_path(path: &Path) -> String {
         .collect::<Vec<_>>()
         .join("/");
 
-    let require = format!("./{path}");
-    format!("{require:?}")
+    format!("./{path}")
+}
+
+fn luau_style_path(path: &Path) -> String {
+    format!("{:?}", luau_path(path))
 }
 
 // This function should be simplified (especially to reduce the number of arguments),
@@ -147,6 +538,36 @@ pub fn get_lib_require_path(
     Ok(luau_style_path(&path))
 }
 
+/// Get the path of a `.luaurc` alias for a library
+pub fn get_lib_alias_path(
+    base_dir: &Path,
+    lib_file: &RelativePathBuf,
+    destination_dir: &Path,
+    use_new_structure: bool,
+) -> String {
+    let path = pathdiff::diff_paths(destination_dir, base_dir).unwrap();
+    if !use_new_structure {
+        return luau_path(&path);
+    }
+
+    let path = lib_file.to_path(path);
+
+    // requires find `init` files and add extensions by themselves
+    let path = match path.file_stem().and_then(|stem| stem.to_str()) {
+        Some("init") => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        _ if matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("lua" | "luau")
+        ) =>
+        {
+            path.with_extension("")
+        }
+        _ => path,
+    };
+
+    luau_path(&path)
+}
+
 /// Generate a linking module for a binary
 pub fn generate_bin_linking_module<P: AsRef<Path>>(package_root: P, require_path: &str) -> String {
     format!(
@@ -180,3 +601,261 @@ pub mod errors {
         RobloxPlaceKindPathNotFound(crate::manifest::target::RobloxPlaceKind),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(file: &str) -> Vec<(String, String)> {
+        get_file_types(file, "module").unwrap().types
+    }
+
+    fn returned_require(file: &str) -> Option<RequirePath> {
+        get_file_types(file, "module").unwrap().returned_require
+    }
+
+    #[test]
+    fn redeclares_types() {
+        assert_eq!(
+            types(
+                r#"
+export type Options = { debug: boolean }
+export type Callback<T> = (T) -> ()
+type Internal = number
+return {}
+"#
+            ),
+            vec![
+                (
+                    "Options".to_string(),
+                    "export type Options = module.Options\n".to_string()
+                ),
+                (
+                    "Callback".to_string(),
+                    "export type Callback<T> = module.Callback<T>\n".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn qualifies_defaults_referencing_exported_types() {
+        assert_eq!(
+            types(
+                r#"
+export type Options = { debug: boolean }
+export type Result<T, E> = { ok: true, value: T } | { ok: false, error: E }
+export type Handler<T = Options, R = Result<T, { Options }>> = (T) -> R
+return {}
+"#
+            )[2],
+            (
+                "Handler".to_string(),
+                "export type Handler<T = module.Options, R = module.Result<T, { module.Options }>> = module.Handler<T, R>\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn redeclares_variadic_packs() {
+        assert_eq!(
+            types(
+                r#"
+export type Signal<T...> = { fire: (T...) -> () }
+export type Callback<A..., R... = ()> = (A...) -> R...
+return {}
+"#
+            ),
+            vec![
+                (
+                    "Signal".to_string(),
+                    "export type Signal<T...> = module.Signal<T...>\n".to_string()
+                ),
+                (
+                    "Callback".to_string(),
+                    "export type Callback<A..., R... = ()> = module.Callback<A..., R...>\n"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_unresolvable_defaults_and_the_ones_before_them() {
+        assert_eq!(
+            types(
+                r#"
+type Internal = { secret: string }
+local value = 1
+export type Pair<K = Internal, V = string> = { key: K, value: V }
+export type Both<A = string, B = Internal> = { a: A, b: B }
+export type Typed<T = typeof(value), U = number> = { t: T, u: U }
+return {}
+"#
+            ),
+            vec![
+                (
+                    "Pair".to_string(),
+                    "export type Pair<K, V = string> = module.Pair<K, V>\n".to_string()
+                ),
+                (
+                    "Both".to_string(),
+                    "export type Both<A, B> = module.Both<A, B>\n".to_string()
+                ),
+                (
+                    "Typed".to_string(),
+                    "export type Typed<T, U = number> = module.Typed<T, U>\n".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_returned_requires() {
+        assert_eq!(
+            returned_require("return require(script.Parent.Foo)"),
+            Some(RequirePath::Instance(RelativePathBuf::from("../Foo")))
+        );
+        assert_eq!(
+            returned_require(r#"return require(script:FindFirstChild("Foo"))"#),
+            Some(RequirePath::Instance(RelativePathBuf::from("Foo")))
+        );
+        assert_eq!(
+            returned_require(r#"return require("./foo")"#),
+            Some(RequirePath::File(RelativePathBuf::from("./foo")))
+        );
+        assert_eq!(
+            returned_require(r#"return require("@self/foo") :: any"#),
+            Some(RequirePath::Instance(RelativePathBuf::from("foo")))
+        );
+        assert_eq!(returned_require(r#"return require("@pkg/foo")"#), None);
+        assert_eq!(
+            returned_require(r#"local foo = require("./foo") return foo"#),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_require_candidates() {
+        let candidates = |require: RequirePath, file: &str| {
+            require
+                .candidates(RelativePath::new(file))
+                .into_iter()
+                .map(|path| path.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            candidates(
+                RequirePath::Instance(RelativePathBuf::from("../Foo")),
+                "src/init.luau"
+            ),
+            ["Foo.luau", "Foo.lua", "Foo/init.luau", "Foo/init.lua"]
+        );
+        assert_eq!(
+            candidates(
+                RequirePath::File(RelativePathBuf::from("./foo")),
+                "src/init.luau"
+            ),
+            [
+                "src/foo.luau",
+                "src/foo.lua",
+                "src/foo/init.luau",
+                "src/foo/init.lua"
+            ]
+        );
+        assert_eq!(
+            candidates(
+                RequirePath::Instance(RelativePathBuf::from("foo")),
+                "src/lib.luau"
+            ),
+            [
+                "src/lib/foo.luau",
+                "src/lib/foo.lua",
+                "src/lib/foo/init.luau",
+                "src/lib/foo/init.lua"
+            ]
+        );
+        assert!(candidates(
+            RequirePath::File(RelativePathBuf::from("../outside")),
+            "init.luau"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn stops_at_require_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        for (file, contents) in [
+            (
+                "init.luau",
+                "export type A = number\nreturn require(\"./b\")",
+            ),
+            ("b.luau", "export type B = string\nreturn require(\"./c\")"),
+            ("c.luau", "export type A = boolean\nreturn require(\"./b\")"),
+        ] {
+            std::fs::write(dir.path().join(file), contents).unwrap();
+        }
+
+        let lib_types = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(super::super::get_lib_types(
+                dir.path(),
+                &RelativePathBuf::from("init.luau"),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            lib_types.modules,
+            [
+                RelativePathBuf::from("b.luau"),
+                RelativePathBuf::from("c.luau")
+            ]
+        );
+        assert_eq!(
+            lib_types.types,
+            ["export type A = module.A\n", "export type B = module_1.B\n"]
+        );
+    }
+
+    /// Compares the linking module generated for a fixture to the expected one
+    fn assert_fixture(require_path: &str, file: &str, expected: &str) {
+        assert_eq!(
+            generate_lib_linking_module(
+                require_path,
+                &[],
+                types(file).into_iter().map(|(_, ty)| ty)
+            ),
+            expected.trim_end()
+        );
+    }
+
+    #[test]
+    fn redeclares_signal_types() {
+        assert_fixture(
+            "script.Parent.Signal",
+            include_str!("fixtures/signal.luau"),
+            include_str!("fixtures/signal.expected.luau"),
+        );
+    }
+
+    #[test]
+    fn redeclares_fusion_types() {
+        assert_fixture(
+            "script.Parent.Types",
+            include_str!("fixtures/fusion_types.luau"),
+            include_str!("fixtures/fusion_types.expected.luau"),
+        );
+    }
+
+    #[test]
+    fn redeclares_trove_types() {
+        assert_fixture(
+            "script.Parent.Trove",
+            include_str!("fixtures/trove.luau"),
+            include_str!("fixtures/trove.expected.luau"),
+        );
+    }
+}
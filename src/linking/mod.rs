@@ -1,45 +1,209 @@
 use crate::{
+    cas::remove_file,
+    install_state::{remove_empty_parents, InstallState},
     linking::generator::get_file_types,
     lockfile::DownloadedGraph,
+    manifest::{target::TargetKind, LinkingStyle},
     scripts::{execute_script, ScriptName},
     source::{
-        fs::{cas_path, store_in_cas},
+        fs::{cas_path, link_file, store_in_cas},
         traits::PackageRef,
     },
     Project, LINK_LIB_NO_FILE_FOUND, PACKAGES_CONTAINER_NAME,
 };
 use fs_err::tokio as fs;
 use futures::future::try_join_all;
+use relative_path::RelativePathBuf;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::OsStr,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 use tokio::task::spawn_blocking;
 
 /// Generates linking modules for a project
 pub mod generator;
 
+/// The name of the file Luau reads a directory's configuration, including aliases, from
+pub const LUAURC_FILE_NAME: &str = ".luaurc";
+
 async fn create_and_canonicalize<P: AsRef<Path>>(path: P) -> std::io::Result<PathBuf> {
     let p = path.as_ref();
     fs::create_dir_all(p).await?;
     p.canonicalize()
 }
 
-async fn write_cas(destination: PathBuf, cas_dir: &Path, contents: &str) -> std::io::Result<()> {
-    let hash = store_in_cas(cas_dir, contents.as_bytes(), |_| async { Ok(()) }).await?;
+/// The linker files written while linking, compared against the ones written before
+struct LinkerFiles<'a> {
+    package_dir: PathBuf,
+    previous: &'a BTreeMap<RelativePathBuf, String>,
+    written: Mutex<BTreeMap<RelativePathBuf, String>>,
+}
+
+impl LinkerFiles<'_> {
+    /// Records a linker file, returning whether it was written with the same contents before
+    fn record(&self, destination: &Path, hash: &str) -> bool {
+        let Some(path) = destination
+            .strip_prefix(&self.package_dir)
+            .ok()
+            .and_then(|path| RelativePathBuf::from_path(path).ok())
+        else {
+            return false;
+        };
+
+        let unchanged = self
+            .previous
+            .get(&path)
+            .is_some_and(|previous| previous == hash);
+        self.written.lock().unwrap().insert(path, hash.to_string());
+
+        unchanged
+    }
+}
+
+/// The types exported by a library, and the modules it returns which declare them
+#[derive(Debug, Default)]
+struct LibTypes {
+    /// The modules the library returns, relative to its package's container folder
+    modules: Vec<RelativePathBuf>,
+    /// The re-declarations of the types
+    types: Vec<String>,
+}
+
+async fn get_lib_types(
+    container_folder: &Path,
+    lib_file: &RelativePathBuf,
+) -> Result<LibTypes, errors::LinkingError> {
+    let mut lib_types = LibTypes::default();
+    let mut names = HashSet::new();
+    let mut file = lib_file.clone();
+
+    loop {
+        let path = file.to_path(container_folder);
+        let is_lib = lib_types.modules.is_empty();
+
+        let contents = match fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && is_lib => {
+                return Err(errors::LinkingError::LibFileNotFound(
+                    path.display().to_string(),
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let module = generator::module_variable(lib_types.modules.len());
+        let file_types = match spawn_blocking(move || get_file_types(&contents, &module))
+            .await
+            .unwrap()
+        {
+            Ok(file_types) => file_types,
+            Err(e) if is_lib => {
+                return Err(errors::LinkingError::FullMoon(
+                    path.display().to_string(),
+                    e,
+                ))
+            }
+            Err(e) => {
+                log::warn!(
+                    "failed to parse {}, not re-exporting its types: {e:?}",
+                    path.display()
+                );
+                lib_types.modules.pop();
+                break;
+            }
+        };
+
+        for (name, declaration) in file_types.types {
+            // the types the library declares itself take precedence
+            if names.insert(name) {
+                lib_types.types.push(declaration);
+            }
+        }
+
+        // the types of a module the library returns are the library's types as well
+        let Some(require) = file_types.returned_require else {
+            break;
+        };
+
+        let mut next = None;
+        for candidate in require.candidates(&file) {
+            if fs::metadata(candidate.to_path(container_folder))
+                .await
+                .is_ok_and(|metadata| metadata.is_file())
+            {
+                next = Some(candidate);
+                break;
+            }
+        }
+
+        match next {
+            Some(next) if next != *lib_file && !lib_types.modules.contains(&next) => {
+                lib_types.modules.push(next.clone());
+                file = next;
+            }
+            _ => break,
+        }
+    }
+
+    Ok(lib_types)
+}
+
+async fn write_cas(
+    destination: PathBuf,
+    project: &Project,
+    contents: &str,
+    linker_files: &LinkerFiles<'_>,
+) -> std::io::Result<()> {
+    let hash = store_in_cas(project.cas_dir(), contents.as_bytes(), |_| async { Ok(()) }).await?;
+
+    // leaving unchanged files alone keeps file watchers from picking them up
+    if linker_files.record(&destination, &hash) && fs::metadata(&destination).await.is_ok() {
+        return Ok(());
+    }
+
+    remove_file(&destination).await?;
 
-    fs::hard_link(cas_path(&hash, cas_dir), destination).await
+    link_file(
+        &cas_path(&hash, project.cas_dir()),
+        &destination,
+        project.link_mode(),
+    )
+    .await
 }
 
 impl Project {
-    /// Links the dependencies of the project
+    /// Links the dependencies of the project. The packages in the state are assumed to be linked
+    /// already, and only the linker files which changed are written. The state's linker files are
+    /// updated, and the ones which are no longer needed are removed
     pub async fn link_dependencies(
         &self,
         graph: &DownloadedGraph,
+        state: &mut InstallState,
     ) -> Result<(), errors::LinkingError> {
         let manifest = self.deser_manifest().await?;
+        let use_luaurc = manifest.linking == LinkingStyle::Luaurc;
+        if use_luaurc
+            && matches!(
+                manifest.target.kind(),
+                TargetKind::Roblox | TargetKind::RobloxServer
+            )
+        {
+            return Err(errors::LinkingError::LuaurcUnsupported(
+                manifest.target.kind(),
+            ));
+        }
+
+        let linker_files = LinkerFiles {
+            package_dir: create_and_canonicalize(self.package_dir()).await?,
+            previous: &state.linker_files,
+            written: Mutex::new(BTreeMap::new()),
+        };
+        let linker_files = &linker_files;
+        let aliases = Mutex::new(BTreeMap::new());
+        let aliases = &aliases;
+        let state_ref = &*state;
         let manifest_target_kind = manifest.target.kind();
         let roblox_sync_config_gen_script = manifest
             .scripts
@@ -51,7 +215,7 @@ impl Project {
                 .map(|(name, versions)| async move {
                     Ok::<_, errors::LinkingError>((name, try_join_all(versions.iter().map(|(version_id, node)| async move {
                         let Some(lib_file) = node.target.lib_path() else {
-                            return Ok((version_id, vec![]));
+                            return Ok::<_, errors::LinkingError>((version_id, LibTypes::default()));
                         };
 
                         let container_folder = node.node.container_folder(
@@ -64,42 +228,24 @@ impl Project {
                         );
 
                         let types = if lib_file.as_str() != LINK_LIB_NO_FILE_FOUND {
-                            let lib_file = lib_file.to_path(&container_folder);
-
-                            let contents = match fs::read_to_string(&lib_file).await {
-                                Ok(contents) => contents,
-                                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                                    return Err(errors::LinkingError::LibFileNotFound(
-                                        lib_file.display().to_string(),
-                                    ));
-                                }
-                                Err(e) => return Err(e.into()),
-                            };
-
-                            let types = match spawn_blocking(move || get_file_types(&contents)).await.unwrap() {
-                                Ok(types) => types,
-                                Err(e) => {
-                                    return Err(errors::LinkingError::FullMoon(
-                                        lib_file.display().to_string(),
-                                        e,
-                                    ))
-                                }
-                            };
+                            let types = get_lib_types(&container_folder, lib_file).await?;
 
-                            log::debug!("{name}@{version_id} has {} exported types", types.len());
+                            log::debug!("{name}@{version_id} has {} exported types", types.types.len());
 
                             types
                         } else {
-                            vec![]
+                            Default::default()
                         };
 
                         if let Some(build_files) = Some(&node.target)
                             .filter(|_| !node.node.pkg_ref.like_wally())
+                            // the sync configs of installed packages were generated already
+                            .filter(|_| !state_ref.is_installed(name, version_id))
                             .and_then(|t| t.build_files())
                         {
                             let Some(script_path) = roblox_sync_config_gen_script else {
                                 log::warn!("not having a `{}` script in the manifest might cause issues with Roblox linking", ScriptName::RobloxSyncConfigGenerator);
-                                return Ok((version_id, vec![]));
+                                return Ok((version_id, LibTypes::default()));
                             };
 
                             execute_script(
@@ -152,7 +298,19 @@ impl Project {
                         );
 
                         if let Some((alias, _, _)) = &node.node.direct.as_ref() {
-                            if let Some((lib_file, types)) =
+                            if let Some(lib_file) = node.target.lib_path().filter(|lib_file| {
+                                use_luaurc && lib_file.as_str() != LINK_LIB_NO_FILE_FOUND
+                            }) {
+                                aliases.lock().unwrap().insert(
+                                    alias.clone(),
+                                    generator::get_lib_alias_path(
+                                        &linker_files.package_dir,
+                                        lib_file,
+                                        &container_folder,
+                                        node.node.pkg_ref.use_new_structure(),
+                                    ),
+                                );
+                            } else if let Some((lib_file, types)) =
                                 node.target.lib_path().and_then(|lib_file| {
                                     package_types
                                         .get(&name)
@@ -160,22 +318,35 @@ impl Project {
                                         .map(|types| (lib_file, types))
                                 })
                             {
+                                let require_path = |lib_file, use_new_structure| {
+                                    generator::get_lib_require_path(
+                                        &node.target.kind(),
+                                        &base_folder,
+                                        lib_file,
+                                        &container_folder,
+                                        use_new_structure,
+                                        &base_folder,
+                                        container_folder.strip_prefix(&base_folder).unwrap(),
+                                        &manifest,
+                                    )
+                                };
+
                                 write_cas(
                                     base_folder.join(format!("{alias}.luau")),
-                                    self.cas_dir(),
+                                    self,
                                     &generator::generate_lib_linking_module(
-                                        &generator::get_lib_require_path(
-                                            &node.target.kind(),
-                                            &base_folder,
+                                        &require_path(
                                             lib_file,
-                                            &container_folder,
                                             node.node.pkg_ref.use_new_structure(),
-                                            &base_folder,
-                                            container_folder.strip_prefix(&base_folder).unwrap(),
-                                            &manifest,
                                         )?,
-                                        types,
+                                        &types
+                                            .modules
+                                            .iter()
+                                            .map(|module| require_path(module, true))
+                                            .collect::<Result<Vec<_>, _>>()?,
+                                        &types.types,
                                     ),
+                                    linker_files,
                                 )
                                 .await?;
                             };
@@ -183,7 +354,7 @@ impl Project {
                             if let Some(bin_file) = node.target.bin_path() {
                                 write_cas(
                                     base_folder.join(format!("{alias}.bin.luau")),
-                                    self.cas_dir(),
+                                    self,
                                     &generator::generate_bin_linking_module(
                                         &container_folder,
                                         &generator::get_bin_require_path(
@@ -192,6 +363,7 @@ impl Project {
                                             &container_folder,
                                         ),
                                     ),
+                                    linker_files,
                                 )
                                 .await?;
                             }
@@ -241,25 +413,39 @@ impl Project {
                         )
                         .await?;
 
+                        let require_path = |lib_file, use_new_structure| {
+                            generator::get_lib_require_path(
+                                &dependency_node.target.kind(),
+                                &linker_folder,
+                                lib_file,
+                                &container_folder,
+                                use_new_structure,
+                                &node_packages_folder,
+                                container_folder.strip_prefix(&base_folder).unwrap(),
+                                &manifest,
+                            )
+                        };
+                        let types = package_types
+                            .get(dependency_name)
+                            .and_then(|v| v.get(dependency_version_id))
+                            .unwrap();
+
                         write_cas(
                             linker_folder.join(format!("{dependency_alias}.luau")),
-                            self.cas_dir(),
+                            self,
                             &generator::generate_lib_linking_module(
-                                &generator::get_lib_require_path(
-                                    &dependency_node.target.kind(),
-                                    &linker_folder,
+                                &require_path(
                                     lib_file,
-                                    &container_folder,
                                     dependency_node.node.pkg_ref.use_new_structure(),
-                                    &node_packages_folder,
-                                    container_folder.strip_prefix(&base_folder).unwrap(),
-                                    &manifest,
                                 )?,
-                                package_types
-                                    .get(dependency_name)
-                                    .and_then(|v| v.get(dependency_version_id))
-                                    .unwrap(),
+                                &types
+                                    .modules
+                                    .iter()
+                                    .map(|module| require_path(module, true))
+                                    .collect::<Result<Vec<_>, _>>()?,
+                                &types.types,
                             ),
+                            linker_files,
                         )
                         .await?;
                     }
@@ -268,11 +454,194 @@ impl Project {
                 }
             })
         }))
-        .await
-        .map(|_| ())
+        .await?;
+
+        let written = linker_files.written.lock().unwrap().clone();
+
+        for path in state.linker_files.keys() {
+            if written.contains_key(path) {
+                continue;
+            }
+
+            let path = path.to_path(&linker_files.package_dir);
+            remove_file(&path).await?;
+            remove_empty_parents(&path, &linker_files.package_dir).await;
+        }
+
+        state.linker_files = written;
+
+        let aliases = std::mem::take(&mut *aliases.lock().unwrap());
+        self.link_luaurc_aliases(aliases, state).await?;
+
+        Ok(())
+    }
+
+    /// Adds the aliases to the project's `.luaurc` file, and removes the ones added before which
+    /// are no longer needed. Aliases defined by the user are left as they are
+    async fn link_luaurc_aliases(
+        &self,
+        aliases: BTreeMap<String, String>,
+        state: &mut InstallState,
+    ) -> Result<(), errors::LinkingError> {
+        if aliases.is_empty() && state.aliases.is_empty() {
+            return Ok(());
+        }
+
+        let path = self.package_dir().join(LUAURC_FILE_NAME);
+        let mut has_comments = false;
+        let mut config = match fs::read_to_string(&path).await {
+            Ok(contents) => {
+                let (contents, comments) = strip_luaurc(&contents);
+                has_comments = comments;
+
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&contents)
+                    .map_err(errors::LinkingError::LuaurcSerde)?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let original = config.clone();
+
+        let serde_json::Value::Object(config_aliases) = config
+            .entry("aliases")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()))
+        else {
+            return Err(errors::LinkingError::LuaurcAliasesNotObject);
+        };
+
+        for (alias, alias_path) in &state.aliases {
+            if config_aliases.get(alias).and_then(|path| path.as_str()) == Some(alias_path) {
+                config_aliases.shift_remove(alias);
+            }
+        }
+
+        let mut linked = BTreeMap::new();
+        for (alias, alias_path) in aliases {
+            match config_aliases.get(&alias) {
+                Some(path) if path.as_str() == Some(&alias_path) => continue,
+                Some(_) => {
+                    log::warn!(
+                        "alias `{alias}` is already defined in {LUAURC_FILE_NAME}, not linking it"
+                    );
+                    continue;
+                }
+                None => {}
+            }
+
+            config_aliases.insert(alias.clone(), serde_json::Value::String(alias_path.clone()));
+            linked.insert(alias, alias_path);
+        }
+
+        if config_aliases.is_empty() && !original.contains_key("aliases") {
+            config.shift_remove("aliases");
+        }
+
+        if config != original {
+            if has_comments {
+                let original_aliases = original
+                    .get("aliases")
+                    .and_then(|aliases| aliases.as_object());
+                let new_aliases = config
+                    .get("aliases")
+                    .and_then(|aliases| aliases.as_object());
+                let changes = new_aliases
+                    .into_iter()
+                    .flatten()
+                    .filter(|(alias, path)| {
+                        original_aliases.and_then(|aliases| aliases.get(*alias)) != Some(*path)
+                    })
+                    .map(|(alias, path)| format!("+ \"{alias}\": {path}"))
+                    .chain(
+                        original_aliases
+                            .into_iter()
+                            .flatten()
+                            .filter(|(alias, _)| {
+                                !new_aliases.is_some_and(|aliases| aliases.contains_key(*alias))
+                            })
+                            .map(|(alias, path)| format!("- \"{alias}\": {path}")),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                log::warn!(
+                    "{LUAURC_FILE_NAME} has comments, which would be lost by updating it. update its aliases yourself:\n{changes}"
+                );
+
+                return Ok(());
+            }
+
+            let contents =
+                serde_json::to_string_pretty(&config).map_err(errors::LinkingError::LuaurcSerde)?;
+            fs::write(&path, contents + "\n").await?;
+        }
+
+        state.aliases = linked;
+
+        Ok(())
     }
 }
 
+/// Removes the comments and trailing commas Luau allows in `.luaurc` files, so that they can be
+/// parsed as JSON. Returns whether there were any comments
+fn strip_luaurc(contents: &str) -> (String, bool) {
+    let mut output = String::with_capacity(contents.len());
+    let mut has_comments = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                output.push(c);
+
+                while let Some(c) = chars.next() {
+                    output.push(c);
+
+                    match c {
+                        '\\' => output.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                has_comments = true;
+
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                has_comments = true;
+                chars.next();
+
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+
+                output.push(' ');
+            }
+            '}' | ']' => {
+                let trimmed_len = output.trim_end().len();
+                if output[..trimmed_len].ends_with(',') {
+                    output.remove(trimmed_len - 1);
+                }
+
+                output.push(c);
+            }
+            c => output.push(c),
+        }
+    }
+
+    (output, has_comments)
+}
+
 /// Errors that can occur while linking dependencies
 pub mod errors {
     use thiserror::Error;
@@ -308,5 +677,100 @@ pub mod errors {
         /// An error occurred while getting the require path for a library
         #[error("error getting require path for library")]
         GetLibRequirePath(#[from] super::generator::errors::GetLibRequirePath),
+
+        /// The project's target doesn't support `.luaurc` aliases
+        #[error("linking with .luaurc aliases isn't supported by the {0} target")]
+        LuaurcUnsupported(crate::manifest::target::TargetKind),
+
+        /// An error occurred while (de)serializing the `.luaurc` file
+        #[error("error (de)serializing .luaurc file")]
+        LuaurcSerde(#[source] serde_json::Error),
+
+        /// The aliases in the `.luaurc` file aren't an object
+        #[error("aliases in .luaurc file aren't an object")]
+        LuaurcAliasesNotObject,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_comments() {
+        assert_eq!(
+            strip_luaurc(
+                r#"{
+    // the aliases
+    "aliases": { /* inline */ "foo": "./foo" } // trailing
+}"#
+            ),
+            (
+                r#"{
+    
+    "aliases": {   "foo": "./foo" } 
+}"#
+                .to_string(),
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_comment_markers_in_strings() {
+        let contents = r#"{ "aliases": { "url": "https://example.com/path", "glob": "/* not a comment */" } }"#;
+
+        assert_eq!(strip_luaurc(contents), (contents.to_string(), false));
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_in_strings() {
+        let contents = r#"{ "aliases": { "quoted": "a \"// b\" /* c */ \\", "next": "d" } }"#;
+
+        assert_eq!(strip_luaurc(contents), (contents.to_string(), false));
+    }
+
+    #[test]
+    fn strips_trailing_commas() {
+        assert_eq!(
+            strip_luaurc(
+                r#"{
+    "globs": ["a", "b",],
+    "aliases": {
+        "foo": "./foo",
+    },
+}"#
+            ),
+            (
+                r#"{
+    "globs": ["a", "b"],
+    "aliases": {
+        "foo": "./foo"
+    }
+}"#
+                .to_string(),
+                false
+            )
+        );
+
+        assert_eq!(
+            strip_luaurc(
+                r#"{ "globs": ["a", // last
+] }"#
+            ),
+            (
+                r#"{ "globs": ["a" 
+] }"#
+                    .to_string(),
+                true
+            )
+        );
+        assert_eq!(
+            strip_luaurc(r#"{ "aliases": { "foo": "a,}", "bar": ",]" } }"#),
+            (
+                r#"{ "aliases": { "foo": "a,}", "bar": ",]" } }"#.to_string(),
+                false
+            )
+        );
     }
 }
@@ -0,0 +1,125 @@
+use crate::{util::hash, Project};
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+};
+
+/// The file used to lock the CAS
+const CAS_LOCK_FILE: &str = ".lock";
+
+/// An advisory lock on a file, which is released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+/// Locks the file at the given path, waiting for other processes holding a conflicting lock on it
+/// to release theirs
+async fn lock_file(
+    path: PathBuf,
+    exclusive: bool,
+    description: String,
+) -> Result<FileLock, errors::LockError> {
+    tokio::task::spawn_blocking(move || {
+        if let Some(parent) = path.parent() {
+            fs_err::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| errors::LockError::Open(path.clone(), e))?;
+
+        let result = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                log::info!("waiting for another process to release the lock on {description}");
+
+                if exclusive {
+                    file.lock()
+                } else {
+                    file.lock_shared()
+                }
+                .map_err(|e| errors::LockError::Lock(path.clone(), e))?;
+            }
+            Err(TryLockError::Error(e)) => return Err(errors::LockError::Lock(path, e)),
+        }
+
+        log::debug!("locked {description}");
+
+        Ok(FileLock { _file: file })
+    })
+    .await
+    .unwrap()
+}
+
+impl Project {
+    /// Locks the project for exclusive use, waiting for other processes using it to finish. This
+    /// should be held while modifying the project's package folders or lockfile
+    pub async fn lock(&self) -> Result<FileLock, errors::LockError> {
+        let package_dir = self.package_dir().to_string_lossy().to_string();
+
+        lock_file(
+            self.data_dir().join("locks").join(hash(&package_dir)),
+            true,
+            format!("project at {package_dir}"),
+        )
+        .await
+    }
+
+    /// Locks a Git repository, waiting for other processes fetching into it to finish. This should
+    /// be held while fetching into the repository, or cloning it
+    pub async fn lock_repo(&self, path: &Path) -> Result<FileLock, errors::LockError> {
+        let path = path.to_string_lossy().to_string();
+
+        lock_file(
+            self.data_dir().join("locks").join(hash(&path)),
+            true,
+            format!("repository at {path}"),
+        )
+        .await
+    }
+
+    /// Locks the CAS, waiting for other processes holding a conflicting lock to finish. Adding to
+    /// the CAS only requires a shared lock, while removing from it requires an exclusive one
+    pub async fn lock_cas(&self, exclusive: bool) -> Result<FileLock, errors::LockError> {
+        lock_file(
+            self.cas_dir().join(CAS_LOCK_FILE),
+            exclusive,
+            format!("CAS at {}", self.cas_dir().display()),
+        )
+        .await
+    }
+}
+
+/// Errors that can occur when locking
+pub mod errors {
+    use std::path::PathBuf;
+    use thiserror::Error;
+
+    /// Errors that can occur when locking a file
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum LockError {
+        /// Error creating the lock file's directory
+        #[error("error creating lock file directory")]
+        Io(#[from] std::io::Error),
+
+        /// Error opening the lock file
+        #[error("error opening lock file {0}")]
+        Open(PathBuf, #[source] std::io::Error),
+
+        /// Error locking the lock file
+        #[error("error locking lock file {0}")]
+        Lock(PathBuf, #[source] std::io::Error),
+    }
+}
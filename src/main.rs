@@ -1,12 +1,15 @@
 #[cfg(feature = "version-management")]
 use crate::cli::version::{check_for_updates, get_or_download_version};
-use crate::cli::{auth::get_tokens, display_err, home_dir, HOME_DIR};
+use crate::cli::{auth::get_tokens, config::read_config, display_err, home_dir, HOME_DIR};
 use anyhow::Context;
 use clap::Parser;
 use fs_err::tokio as fs;
 use indicatif::MultiProgress;
 use indicatif_log_bridge::LogWrapper;
-use pesde::{matching_globs, AuthConfig, Project, MANIFEST_FILE_NAME};
+use pesde::{
+    download::DownloadConfig, matching_globs, source::fs::LinkMode, AuthConfig, GitCredentials,
+    NetworkConfig, Project, MANIFEST_FILE_NAME,
+};
 use std::{
     collections::HashSet,
     path::{Path, PathBuf},
@@ -31,7 +34,7 @@ struct Cli {
     subcommand: cli::commands::Subcommand,
 }
 
-async fn get_linkable_dir(path: &Path) -> PathBuf {
+async fn get_linkable_dir(path: &Path) -> Option<PathBuf> {
     let mut curr_path = PathBuf::new();
     let file_to_try = NamedTempFile::new_in(path).expect("failed to create temporary file");
 
@@ -67,14 +70,11 @@ async fn get_linkable_dir(path: &Path) -> PathBuf {
                 );
             }
 
-            return curr_path;
+            return Some(curr_path);
         }
     }
 
-    panic!(
-        "couldn't find a linkable directory for any point in {}",
-        curr_path.display()
-    );
+    None
 }
 
 async fn run() -> anyhow::Result<()> {
@@ -195,26 +195,72 @@ async fn run() -> anyhow::Result<()> {
         .await
         .expect("failed to create data directory");
 
-    let cas_dir = get_linkable_dir(&project_root_dir).await.join(HOME_DIR);
+    let config = read_config().await?;
+    let link_mode = config.link_mode.unwrap_or_default();
+
+    // hard links and clones only work within a volume, so the CAS is kept on the project's volume
+    // if possible. otherwise, the files are copied from the one in the data directory
+    let linkable_dir = match link_mode {
+        LinkMode::Hardlink | LinkMode::Reflink => get_linkable_dir(&project_root_dir).await,
+        LinkMode::Symlink | LinkMode::Copy => None,
+    };
 
-    let cas_dir = if cas_dir == home_dir {
-        &data_dir
-    } else {
-        &cas_dir
+    let cas_dir = match linkable_dir.map(|dir| dir.join(HOME_DIR)) {
+        Some(cas_dir) if cas_dir != home_dir => cas_dir,
+        _ => data_dir.clone(),
     }
     .join("cas");
 
-    log::debug!("using cas dir in {}", cas_dir.display());
+    log::debug!("using cas dir in {} ({link_mode})", cas_dir.display());
+    let network_config = NetworkConfig::new()
+        .with_proxy(config.proxy())
+        .with_no_proxy(config.no_proxy())
+        .with_ca_file(config.ca_file());
 
     let project = Project::new(
         project_root_dir,
         project_workspace_dir,
         data_dir,
         cas_dir,
-        AuthConfig::new().with_tokens(get_tokens().await?.0),
-    );
+        AuthConfig::new()
+            .with_tokens(get_tokens().await?.0)
+            .with_host_git_credentials(config.git_credentials.into_iter().filter_map(
+                |(host, credentials)| {
+                    credentials
+                        .into_credentials()
+                        .map(|credentials| (host, credentials))
+                },
+            )),
+    )
+    .with_mirrors(
+        config
+            .mirrors
+            .into_iter()
+            .map(|(index, mirrors)| {
+                gix::Url::try_from(index.as_str())
+                    .with_context(|| format!("invalid index url `{index}` in mirrors"))
+                    .map(|index| (index, mirrors))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    )
+    .with_link_mode(link_mode)
+    .with_network_config(network_config)
+    .with_download_config({
+        let mut download_config = DownloadConfig::new();
+
+        if let Some(max_concurrency) = config.max_concurrent_downloads {
+            download_config = download_config.with_max_concurrency(max_concurrency);
+        }
+
+        if let Some(max_retries) = config.max_download_retries {
+            download_config = download_config.with_max_retries(max_retries);
+        }
+
+        download_config
+    });
 
     let reqwest = {
+        let network_config = project.network_config();
         let mut headers = reqwest::header::HeaderMap::new();
 
         headers.insert(
@@ -224,14 +270,37 @@ async fn run() -> anyhow::Result<()> {
                 .context("failed to create accept header")?,
         );
 
-        reqwest::Client::builder()
+        let mut builder = reqwest::Client::builder()
             .user_agent(concat!(
                 env!("CARGO_PKG_NAME"),
                 "/",
                 env!("CARGO_PKG_VERSION")
             ))
-            .default_headers(headers)
-            .build()?
+            .default_headers(headers);
+
+        if let Some(proxy) = network_config.proxy() {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy)
+                    .with_context(|| format!("invalid proxy `{proxy}`"))?
+                    .no_proxy(
+                        network_config
+                            .no_proxy()
+                            .and_then(reqwest::NoProxy::from_string),
+                    ),
+            );
+        }
+
+        if let Some(ca_file) = network_config.ca_file() {
+            let bundle = fs::read(ca_file).await.context("failed to read CA file")?;
+
+            for certificate in reqwest::Certificate::from_pem_bundle(&bundle)
+                .with_context(|| format!("failed to parse CA file {}", ca_file.display()))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build()?
     };
 
     #[cfg(feature = "version-management")]
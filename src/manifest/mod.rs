@@ -78,6 +78,9 @@ pub struct Manifest {
     /// The Roblox place of this project
     #[serde(default, skip_serializing)]
     pub place: BTreeMap<target::RobloxPlaceKind, String>,
+    /// How the project's dependencies are linked
+    #[serde(default, skip_serializing)]
+    pub linking: LinkingStyle,
 
     /// The standard dependencies of the package
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
@@ -93,6 +96,18 @@ pub struct Manifest {
     pub user_defined_fields: HashMap<String, toml::Value>,
 }
 
+/// How a project's direct dependencies are made available to it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkingStyle {
+    /// Linker modules requiring the dependencies are generated in the packages folders
+    #[default]
+    Modules,
+    /// Aliases to the dependencies are added to the project's `.luaurc` file, so that they can be
+    /// required with `@alias`. Only supported by Lune and Luau targets
+    Luaurc,
+}
+
 /// A dependency type
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
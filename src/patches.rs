@@ -1,4 +1,7 @@
-use crate::{lockfile::DownloadedGraph, Project, MANIFEST_FILE_NAME, PACKAGES_CONTAINER_NAME};
+use crate::{
+    install_state::InstallState, lockfile::DownloadedGraph, Project, MANIFEST_FILE_NAME,
+    PACKAGES_CONTAINER_NAME,
+};
 use fs_err::tokio as fs;
 use git2::{ApplyLocation, Diff, DiffFormat, DiffLineType, Repository, Signature};
 use relative_path::RelativePathBuf;
@@ -46,7 +49,14 @@ pub fn create_patch<P: AsRef<Path>>(dir: P) -> Result<Vec<u8>, git2::Error> {
     checkout_builder.path(MANIFEST_FILE_NAME);
     repo.checkout_tree(original.as_object(), Some(&mut checkout_builder))?;
 
-    let diff = repo.diff_tree_to_workdir(Some(&original), None)?;
+    // include untracked files, so that files added to the package are part of the patch
+    let mut diff_options = git2::DiffOptions::new();
+    diff_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+
+    let diff = repo.diff_tree_to_workdir(Some(&original), Some(&mut diff_options))?;
 
     diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
         if matches!(
@@ -68,10 +78,12 @@ pub fn create_patch<P: AsRef<Path>>(dir: P) -> Result<Vec<u8>, git2::Error> {
 }
 
 impl Project {
-    /// Apply patches to the project's dependencies
+    /// Apply patches to the project's dependencies. The packages in the state are installed
+    /// already, and so are patched already
     pub async fn apply_patches(
         &self,
         graph: &DownloadedGraph,
+        state: &InstallState,
     ) -> Result<
         tokio::sync::mpsc::Receiver<Result<String, errors::ApplyPatchesError>>,
         errors::ApplyPatchesError,
@@ -104,6 +116,12 @@ impl Project {
                     continue;
                 };
 
+                if state.is_installed(&name, &version_id) {
+                    log::debug!("{name}@{version_id} is already patched");
+                    tx.send(Ok(format!("{name}@{version_id}"))).await.unwrap();
+                    continue;
+                }
+
                 let container_folder = node.node.container_folder(
                     &self
                         .package_dir()
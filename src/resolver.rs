@@ -199,11 +199,30 @@ impl Project {
                 source.refresh(self).await.map_err(|e| Box::new(e.into()))?;
             }
 
-            let (name, resolved) = source
+            let (name, mut resolved) = source
                 .resolve(&specifier, self, target, refreshed_sources)
                 .await
                 .map_err(|e| Box::new(e.into()))?;
 
+            // yanked versions aren't offered by their source, but can still be used if they're locked
+            if let (Some(previous_graph), DependencySpecifiers::Pesde(specifier)) =
+                (previous_graph, &specifier)
+            {
+                for (version_id, node) in previous_graph.get(&name).into_iter().flatten() {
+                    if !resolved.contains_key(version_id)
+                        && specifier.version.matches(version_id.version())
+                        && specifier.target.unwrap_or(target) == *version_id.target()
+                        && node.pkg_ref.source() == source
+                    {
+                        log::debug!(
+                            "{}using locked {name}@{version_id} which isn't offered by its source",
+                            "\t".repeat(depth)
+                        );
+                        resolved.insert(version_id.clone(), node.pkg_ref.clone());
+                    }
+                }
+            }
+
             let Some(target_version_id) = graph
                 .get(&name)
                 .and_then(|versions| {
@@ -6,13 +6,16 @@ use fs_err::tokio as fs;
 use futures::future::try_join_all;
 use relative_path::RelativePathBuf;
 use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
 use sha2::{Digest, Sha256};
 use std::{
     collections::BTreeMap,
+    fmt::{Display, Formatter},
     future::Future,
     path::{Path, PathBuf},
+    str::FromStr,
 };
-use tempfile::Builder;
+use tempfile::{Builder, TempPath};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     pin,
@@ -40,6 +43,47 @@ pub enum PackageFS {
     Copy(PathBuf, TargetKind),
 }
 
+/// How files are placed in a project from the CAS
+#[derive(
+    SerializeDisplay, DeserializeFromStr, Debug, Clone, Copy, PartialEq, Eq, Hash, Default,
+)]
+pub enum LinkMode {
+    /// Hard link files
+    #[default]
+    Hardlink,
+    /// Clone files using copy-on-write, where the file system supports it
+    Reflink,
+    /// Symlink files
+    Symlink,
+    /// Copy files
+    Copy,
+}
+
+impl Display for LinkMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkMode::Hardlink => write!(f, "hardlink"),
+            LinkMode::Reflink => write!(f, "reflink"),
+            LinkMode::Symlink => write!(f, "symlink"),
+            LinkMode::Copy => write!(f, "copy"),
+        }
+    }
+}
+
+impl FromStr for LinkMode {
+    type Err = errors::LinkModeFromStr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hardlink" => Ok(Self::Hardlink),
+            "reflink" => Ok(Self::Reflink),
+            "symlink" => Ok(Self::Symlink),
+            "copy" => Ok(Self::Copy),
+            s => Err(errors::LinkModeFromStr::Unknown(s.to_string())),
+        }
+    }
+}
+
 async fn set_readonly(path: &Path, readonly: bool) -> std::io::Result<()> {
     // on Windows, file deletion is disallowed if the file is read-only which breaks multiple features
     #[cfg(windows)]
@@ -72,6 +116,80 @@ pub(crate) fn cas_path(hash: &str, cas_dir: &Path) -> PathBuf {
     cas_dir.join(prefix).join(rest)
 }
 
+async fn copy_file(cas_file_path: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::copy(cas_file_path, destination).await?;
+    set_readonly(destination, false).await
+}
+
+/// Whether linking failed because of the file system, rather than the files themselves, such as
+/// when the CAS is on a different volume than the project
+fn should_fall_back(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::CrossesDevices
+            | std::io::ErrorKind::Unsupported
+            | std::io::ErrorKind::PermissionDenied
+            | std::io::ErrorKind::InvalidInput
+    )
+}
+
+/// Places a file from the CAS at the destination using the given link mode, falling back to
+/// copying it if the file system doesn't allow linking it
+pub(crate) async fn link_file(
+    cas_file_path: &Path,
+    destination: &Path,
+    link_mode: LinkMode,
+) -> std::io::Result<()> {
+    let result = match link_mode {
+        LinkMode::Hardlink => fs::hard_link(cas_file_path, destination).await,
+        LinkMode::Reflink => {
+            let cas_file_path = cas_file_path.to_path_buf();
+            let destination = destination.to_path_buf();
+            tokio::task::spawn_blocking(move || reflink_copy::reflink(cas_file_path, destination))
+                .await
+                .unwrap()
+        }
+        #[cfg(windows)]
+        LinkMode::Symlink => fs::symlink_file(cas_file_path, destination).await,
+        #[cfg(unix)]
+        LinkMode::Symlink => fs::symlink(cas_file_path, destination).await,
+        LinkMode::Copy => return copy_file(cas_file_path, destination).await,
+    };
+
+    match result {
+        // clones are independent of the CAS, so like copies they can be writable
+        Ok(()) if link_mode == LinkMode::Reflink => set_readonly(destination, false).await,
+        Err(e) if should_fall_back(&e) => {
+            log::debug!(
+                "failed to {link_mode} {} to {}, copying instead: {e}",
+                cas_file_path.display(),
+                destination.display()
+            );
+
+            copy_file(cas_file_path, destination).await
+        }
+        result => result,
+    }
+}
+
+/// Writes a file in the CAS by renaming a temporary file into place, so that other processes never
+/// read it partially written
+pub(crate) async fn write_atomically<C: AsRef<[u8]>>(
+    cas_dir: &Path,
+    destination: &Path,
+    contents: C,
+) -> std::io::Result<()> {
+    let tmp_dir = cas_dir.join(".tmp");
+    fs::create_dir_all(&tmp_dir).await?;
+
+    let temp_path = Builder::new()
+        .make_in(&tmp_dir, |_| Ok(()))?
+        .into_temp_path();
+    fs::write(temp_path.to_path_buf(), contents).await?;
+
+    temp_path.persist(destination).map_err(|e| e.error)
+}
+
 pub(crate) async fn store_in_cas<
     R: tokio::io::AsyncRead + Unpin,
     P: AsRef<Path>,
@@ -79,10 +197,28 @@ pub(crate) async fn store_in_cas<
     F: Future<Output = std::io::Result<()>>,
 >(
     cas_dir: P,
+    contents: R,
+    bytes_cb: C,
+) -> std::io::Result<String> {
+    let (temp_path, hash) = stage_in_cas(cas_dir.as_ref(), contents, bytes_cb).await?;
+    persist_in_cas(cas_dir.as_ref(), temp_path, &hash).await?;
+
+    Ok(hash)
+}
+
+/// Writes the contents to a temporary file in the CAS, returning it and the hash of the contents.
+/// The file is removed when the returned path is dropped, unless it is persisted with
+/// [persist_in_cas]
+pub(crate) async fn stage_in_cas<
+    R: tokio::io::AsyncRead + Unpin,
+    C: FnMut(Vec<u8>) -> F,
+    F: Future<Output = std::io::Result<()>>,
+>(
+    cas_dir: &Path,
     mut contents: R,
     mut bytes_cb: C,
-) -> std::io::Result<String> {
-    let tmp_dir = cas_dir.as_ref().join(".tmp");
+) -> std::io::Result<(TempPath, String)> {
+    let tmp_dir = cas_dir.join(".tmp");
     fs::create_dir_all(&tmp_dir).await?;
     let mut hasher = Sha256::new();
     let mut buf = [0; 8 * 1024];
@@ -107,9 +243,18 @@ pub(crate) async fn store_in_cas<
         file_writer.write_all(bytes).await?;
     }
 
-    let hash = format!("{:x}", hasher.finalize());
+    file_writer.flush().await?;
+
+    Ok((temp_path, format!("{:x}", hasher.finalize())))
+}
 
-    let cas_path = cas_path(&hash, cas_dir.as_ref());
+/// Moves a file staged with [stage_in_cas] into the CAS
+pub(crate) async fn persist_in_cas(
+    cas_dir: &Path,
+    temp_path: TempPath,
+    hash: &str,
+) -> std::io::Result<()> {
+    let cas_path = cas_path(hash, cas_dir);
     fs::create_dir_all(cas_path.parent().unwrap()).await?;
 
     match temp_path.persist_noclobber(&cas_path) {
@@ -120,16 +265,17 @@ pub(crate) async fn store_in_cas<
         Err(e) => return Err(e.error),
     };
 
-    Ok(hash)
+    Ok(())
 }
 
 impl PackageFS {
-    /// Write the package to the given destination
+    /// Write the package to the given destination, placing the files from the CAS using the given
+    /// link mode
     pub async fn write_to<P: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         destination: P,
         cas_path: Q,
-        link: bool,
+        link_mode: LinkMode,
     ) -> std::io::Result<()> {
         match self {
             PackageFS::CAS(entries) => {
@@ -149,12 +295,7 @@ impl PackageFS {
                                 let (prefix, rest) = hash.split_at(2);
                                 let cas_file_path = cas_path.join(prefix).join(rest);
 
-                                if link {
-                                    fs::hard_link(cas_file_path, path).await?;
-                                } else {
-                                    fs::copy(cas_file_path, &path).await?;
-                                    set_readonly(&path, false).await?;
-                                }
+                                link_file(&cas_file_path, &path, link_mode).await?;
                             }
                             FSEntry::Directory => {
                                 fs::create_dir_all(path).await?;
@@ -225,3 +366,17 @@ impl PackageFS {
         fs::read_to_string(cas_file_path).await.ok()
     }
 }
+
+/// Errors that can occur when interacting with packages' filesystems
+pub mod errors {
+    use thiserror::Error;
+
+    /// Errors that can occur when parsing a link mode from a string
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum LinkModeFromStr {
+        /// The link mode is unknown
+        #[error("unknown link mode {0}")]
+        Unknown(String),
+    }
+}
@@ -1,24 +1,38 @@
 use crate::{
+    download::DownloadProgressReporter,
+    locks::FileLock,
     manifest::{
         target::{Target, TargetKind},
         Manifest,
     },
     names::PackageNames,
     source::{
-        fs::{store_in_cas, FSEntry, PackageFS},
-        git::{pkg_ref::GitPackageRef, specifier::GitDependencySpecifier},
+        fs::{store_in_cas, write_atomically, FSEntry, PackageFS},
+        git::{
+            pkg_ref::{GitPackageRef, GitSubmodule},
+            specifier::GitDependencySpecifier,
+        },
         git_index::{read_file, GitBasedSource},
         specifiers::DependencySpecifiers,
         traits::PackageRef,
         PackageSource, PackageSources, ResolveResult, VersionId, IGNORED_DIRS, IGNORED_FILES,
     },
-    util::hash,
-    Project, DEFAULT_INDEX_NAME, LOCKFILE_FILE_NAME, MANIFEST_FILE_NAME,
+    util::{authenticate_conn, credentials_for, git_config_overrides, hash},
+    AuthConfig, Project, DEFAULT_INDEX_NAME, LOCKFILE_FILE_NAME, MANIFEST_FILE_NAME,
 };
 use fs_err::tokio as fs;
 use futures::future::try_join_all;
-use gix::{bstr::BStr, traverse::tree::Recorder, ObjectId, Url};
-use relative_path::RelativePathBuf;
+use gix::{
+    bstr::{BStr, ByteSlice},
+    prelude::ObjectIdExt,
+    remote::{
+        fetch::{Shallow, Tags},
+        Direction,
+    },
+    traverse::tree::Recorder,
+    ObjectId, Url,
+};
+use relative_path::{RelativePath, RelativePathBuf};
 use std::{
     collections::{BTreeMap, HashSet},
     fmt::Debug,
@@ -33,6 +47,10 @@ pub mod pkg_ref;
 /// The Git dependency specifier
 pub mod specifier;
 
+// fetching into a repository locks it, so fetches into the same repository are done one at a time
+static FETCH_LOCKS: std::sync::Mutex<BTreeMap<PathBuf, Arc<Mutex<()>>>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
 /// The Git package source
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct GitPackageSource {
@@ -61,6 +79,706 @@ impl GitPackageSource {
     fn as_bytes(&self) -> Vec<u8> {
         self.repo_url.to_bstring().to_vec()
     }
+
+    fn open_options(&self, project: &Project) -> gix::open::Options {
+        gix::open::Options::default()
+            .config_overrides(git_config_overrides(&self.repo_url, project))
+    }
+
+    /// Locks the repository for fetching, both within this process and across processes
+    async fn lock(
+        &self,
+        project: &Project,
+    ) -> Result<(tokio::sync::OwnedMutexGuard<()>, FileLock), errors::FetchError> {
+        let path = self.path(project);
+        let lock = FETCH_LOCKS
+            .lock()
+            .unwrap()
+            .entry(path.clone())
+            .or_default()
+            .clone();
+        let guard = lock.lock_owned().await;
+
+        Ok((guard, project.lock_repo(&path).await?))
+    }
+
+    /// Fetches the blobs in `ids` which blob-less fetches left out of the repository
+    async fn fetch_blobs(
+        &self,
+        project: &Project,
+        ids: Vec<ObjectId>,
+    ) -> Result<(), errors::FetchError> {
+        let repo = gix::open_opts(self.path(project), self.open_options(project))
+            .map_err(|e| {
+                errors::FetchError::OpenRepo(Box::new(self.repo_url.clone()), Box::new(e))
+            })?
+            .into_sync();
+        let repo_url = self.repo_url.clone();
+        let auth_config = project.auth_config().clone();
+
+        let _guard = self.lock(project).await?;
+
+        spawn_blocking(move || {
+            let repo = repo.to_thread_local();
+
+            let missing = ids
+                .into_iter()
+                .filter(|id| !repo.has_object(id))
+                .collect::<HashSet<_>>();
+            if missing.is_empty() {
+                return Ok(());
+            }
+
+            log::debug!("fetching {} missing blobs from {repo_url}", missing.len());
+
+            fetch_objects(
+                &repo,
+                &repo_url,
+                &auth_config,
+                missing,
+                Shallow::NoChange,
+                None,
+            )
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Fetches `rev` from the repository without its history, returning the id of the object it points to.
+    /// If `rev` is `None` or isn't a reference or full object id of the remote, the whole repository is fetched
+    /// and `None` is returned, meaning `rev` has to be resolved locally
+    async fn fetch_rev(
+        &self,
+        project: &Project,
+        rev: Option<&str>,
+    ) -> Result<Option<ObjectId>, errors::FetchError> {
+        let repo = gix::open_opts(self.path(project), self.open_options(project))
+            .map_err(|e| {
+                errors::FetchError::OpenRepo(Box::new(self.repo_url.clone()), Box::new(e))
+            })?
+            .into_sync();
+        let repo_url = self.repo_url.clone();
+        let auth_config = project.auth_config().clone();
+        let rev = rev.map(ToString::to_string);
+
+        let _guard = self.lock(project).await?;
+
+        spawn_blocking(move || {
+            let repo = repo.to_thread_local();
+
+            if let Some(rev) = &rev {
+                if let Ok(id) = ObjectId::from_hex(rev.as_bytes()) {
+                    if repo.has_object(id) {
+                        log::debug!("{repo_url}#{rev} is already fetched");
+                        return Ok(Some(id));
+                    }
+                }
+            }
+
+            if let Some(rev) = rev
+                .as_deref()
+                .filter(|rev| gix::refs::PartialName::try_from(*rev).is_ok())
+            {
+                if let Some(id) = fetch(
+                    &repo,
+                    &repo_url,
+                    &auth_config,
+                    [rev],
+                    Shallow::DepthAtRemote(1.try_into().unwrap()),
+                )?
+                .into_iter()
+                .next()
+                {
+                    return Ok(Some(id));
+                }
+            }
+
+            log::debug!("fetching the whole repository {repo_url}");
+
+            fetch(
+                &repo,
+                &repo_url,
+                &auth_config,
+                ["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"],
+                if repo.is_shallow() {
+                    Shallow::undo()
+                } else {
+                    Shallow::NoChange
+                },
+            )?;
+
+            Ok(None)
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Fetches the blobs at `paths` in the tree of `commit_id` which blob-less fetches left out
+    async fn fetch_blobs_at(
+        &self,
+        project: &Project,
+        commit_id: ObjectId,
+        paths: &[RelativePathBuf],
+    ) -> Result<(), errors::ResolveError> {
+        let ids = {
+            let repo = gix::open_opts(self.path(project), self.open_options(project))
+                .map_err(|e| errors::ResolveError::OpenRepo(Box::new(self.repo_url.clone()), e))?;
+
+            let tree = repo
+                .find_object(commit_id)
+                .map_err(|e| {
+                    errors::ResolveError::ParseRevToObject(Box::new(self.repo_url.clone()), e)
+                })?
+                .peel_to_tree()
+                .map_err(|e| {
+                    errors::ResolveError::ParseObjectToTree(Box::new(self.repo_url.clone()), e)
+                })?;
+
+            let mut ids = vec![];
+
+            for path in paths {
+                let entry = tree.lookup_entry_by_path(path.as_str()).map_err(|e| {
+                    errors::ResolveError::ReadTreeEntry(
+                        Box::new(self.repo_url.clone()),
+                        path.clone(),
+                        e,
+                    )
+                })?;
+
+                if let Some(entry) = entry.filter(|entry| entry.mode().is_blob_or_symlink()) {
+                    ids.push(entry.object_id());
+                }
+            }
+
+            ids
+        };
+
+        self.fetch_blobs(project, ids).await?;
+
+        Ok(())
+    }
+
+    /// Finds the submodules of the tree at `path` in `commit_id`, fetching them to find their submodules as well.
+    /// The submodules' paths are relative to `path`
+    async fn resolve_submodules(
+        &self,
+        project: &Project,
+        commit_id: ObjectId,
+        path: Option<RelativePathBuf>,
+    ) -> Result<BTreeMap<RelativePathBuf, GitSubmodule>, errors::ResolveError> {
+        self.fetch_blobs_at(project, commit_id, &[RelativePathBuf::from(".gitmodules")])
+            .await?;
+
+        let found = {
+            let repo = gix::open_opts(self.path(project), self.open_options(project))
+                .map_err(|e| errors::ResolveError::OpenRepo(Box::new(self.repo_url.clone()), e))?;
+
+            let root_tree = repo
+                .find_object(commit_id)
+                .map_err(|e| {
+                    errors::ResolveError::ParseRevToObject(Box::new(self.repo_url.clone()), e)
+                })?
+                .peel_to_tree()
+                .map_err(|e| {
+                    errors::ResolveError::ParseObjectToTree(Box::new(self.repo_url.clone()), e)
+                })?;
+
+            let Some(gitmodules) = read_file(&root_tree, [".gitmodules"]).map_err(|e| {
+                errors::ResolveError::ReadGitmodules(Box::new(self.repo_url.clone()), e)
+            })?
+            else {
+                return Ok(BTreeMap::new());
+            };
+
+            let modules =
+                gix::submodule::File::from_bytes(gitmodules.as_bytes(), None, &Default::default())
+                    .map_err(|e| {
+                        errors::ResolveError::ParseGitmodules(Box::new(self.repo_url.clone()), e)
+                    })?;
+
+            let mut found = vec![];
+
+            for name in modules.names() {
+                let submodule_path = modules.path(name).map_err(|e| {
+                    errors::ResolveError::SubmodulePath(Box::new(self.repo_url.clone()), e)
+                })?;
+                let submodule_path =
+                    RelativePath::new(submodule_path.to_str_lossy().as_ref()).normalize();
+
+                let relative_path = match &path {
+                    Some(path) => match submodule_path.strip_prefix(path.normalize()) {
+                        Ok(relative_path) => relative_path.to_relative_path_buf(),
+                        Err(_) => continue,
+                    },
+                    None => submodule_path.clone(),
+                };
+
+                // submodules may stay in `.gitmodules` after being removed from the tree
+                let commit_id = match root_tree
+                    .lookup_entry_by_path(submodule_path.as_str())
+                    .map_err(|e| {
+                        errors::ResolveError::ReadTreeEntry(
+                            Box::new(self.repo_url.clone()),
+                            submodule_path.clone(),
+                            e,
+                        )
+                    })? {
+                    Some(entry) if entry.mode().is_commit() => entry.object_id(),
+                    _ => continue,
+                };
+
+                let url = modules
+                    .config()
+                    .string(format!("submodule.{name}.url"))
+                    .ok_or_else(|| {
+                        errors::ResolveError::NoSubmoduleUrl(
+                            name.to_string(),
+                            Box::new(self.repo_url.clone()),
+                        )
+                    })?;
+                let url = submodule_url(&self.repo_url, &url.to_str_lossy()).map_err(|e| {
+                    errors::ResolveError::ParseSubmoduleUrl(
+                        name.to_string(),
+                        Box::new(self.repo_url.clone()),
+                        e,
+                    )
+                })?;
+
+                found.push((relative_path, url, commit_id));
+            }
+
+            found
+        };
+
+        let mut submodules = BTreeMap::new();
+
+        for (relative_path, url, commit_id) in found {
+            let source = GitPackageSource::new(url.clone());
+            PackageSource::refresh(&source, project)
+                .await
+                .map_err(|e| {
+                    errors::ResolveError::RefreshSubmodule(Box::new(url.clone()), Box::new(e))
+                })?;
+            source
+                .fetch_rev(project, Some(&commit_id.to_string()))
+                .await?;
+
+            let nested = Box::pin(source.resolve_submodules(project, commit_id, None)).await?;
+            submodules.extend(
+                nested
+                    .into_iter()
+                    .map(|(path, submodule)| (relative_path.join(path), submodule)),
+            );
+
+            submodules.insert(
+                relative_path,
+                GitSubmodule {
+                    repo: url,
+                    commit_id: commit_id.to_string(),
+                },
+            );
+        }
+
+        Ok(submodules)
+    }
+
+    /// Reads the contents of the tree `id` points to, skipping submodules
+    async fn read_tree(
+        &self,
+        project: &Project,
+        id: ObjectId,
+    ) -> Result<Vec<(RelativePathBuf, Option<Vec<u8>>)>, errors::DownloadError> {
+        let repo = gix::open_opts(self.path(project), self.open_options(project))
+            .map_err(|e| errors::DownloadError::OpenRepo(Box::new(self.repo_url.clone()), e))?
+            .into_sync();
+        let repo_url = self.repo_url.clone();
+
+        let (repo, records) = spawn_blocking(move || {
+            let repo = repo.to_thread_local();
+
+            let mut recorder = Recorder::default();
+
+            {
+                let object = match repo.find_object(id) {
+                    Ok(object) => object,
+                    Err(e) => {
+                        return Err(errors::DownloadError::ParseOidToObject(
+                            id,
+                            Box::new(repo_url),
+                            e,
+                        ))
+                    }
+                };
+
+                let tree = match object.peel_to_tree() {
+                    Ok(tree) => tree,
+                    Err(e) => {
+                        return Err(errors::DownloadError::ParseObjectToTree(
+                            Box::new(repo_url),
+                            e,
+                        ))
+                    }
+                };
+
+                if let Err(e) = tree.traverse().breadthfirst(&mut recorder) {
+                    return Err(errors::DownloadError::TraverseTree(Box::new(repo_url), e));
+                }
+            }
+
+            Ok::<_, errors::DownloadError>((repo.into_sync(), recorder.records))
+        })
+        .await
+        .unwrap()?;
+
+        self.fetch_blobs(
+            project,
+            records
+                .iter()
+                .filter(|entry| entry.mode.is_blob_or_symlink())
+                .map(|entry| entry.oid)
+                .collect(),
+        )
+        .await?;
+
+        let repo = repo.to_thread_local();
+
+        records
+            .into_iter()
+            // submodules' contents are read from their own repositories
+            .filter(|entry| !entry.mode.is_commit())
+            .map(|entry| {
+                let object = repo.find_object(entry.oid).map_err(|e| {
+                    errors::DownloadError::ParseOidToObject(
+                        entry.oid,
+                        Box::new(self.repo_url.clone()),
+                        e,
+                    )
+                })?;
+
+                Ok((
+                    RelativePathBuf::from(entry.filepath.to_string()),
+                    if matches!(object.kind, gix::object::Kind::Tree) {
+                        None
+                    } else {
+                        Some(object.data.clone())
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Resolves a submodule's URL, which may be relative to the URL of its parent repository
+fn submodule_url(parent: &Url, url: &str) -> Result<Url, gix::url::parse::Error> {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return Url::try_from(url);
+    }
+
+    let mut path = parent.path.to_string().trim_end_matches('/').to_string();
+    let mut rest = url;
+
+    loop {
+        if let Some(stripped) = rest.strip_prefix("./") {
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("../") {
+            rest = stripped;
+            path.truncate(path.rfind('/').unwrap_or(0));
+        } else {
+            break;
+        }
+    }
+
+    let mut url = parent.clone();
+    url.path = if path.is_empty() {
+        rest.into()
+    } else {
+        format!("{path}/{rest}").into()
+    };
+
+    Ok(url)
+}
+
+/// Fetches `refspecs` from the repository, returning the ids of the objects they matched.
+/// If the remote supports it, blobs are left out and fetched when they're read
+fn fetch<'a>(
+    repo: &gix::Repository,
+    repo_url: &gix::Url,
+    auth_config: &AuthConfig,
+    refspecs: impl IntoIterator<Item = &'a str>,
+    shallow: Shallow,
+) -> Result<Vec<ObjectId>, errors::FetchError> {
+    let remote = repo
+        .remote_at(repo_url.clone())
+        .map_err(|e| errors::FetchError::Remote(Box::new(repo_url.clone()), Box::new(e)))?
+        .with_fetch_tags(Tags::None)
+        .with_refspecs(refspecs, Direction::Fetch)
+        .map_err(|e| errors::FetchError::RefSpec(Box::new(repo_url.clone()), Box::new(e)))?;
+
+    let mut connection = remote
+        .connect(Direction::Fetch)
+        .map_err(|e| errors::FetchError::Connect(Box::new(repo_url.clone()), Box::new(e)))?;
+
+    authenticate_conn(&mut connection, auth_config);
+
+    let fetch = connection
+        .prepare_fetch(gix::progress::Discard, Default::default())
+        .map_err(|e| errors::FetchError::PrepareFetch(Box::new(repo_url.clone()), Box::new(e)))?
+        .with_shallow(shallow.clone());
+
+    let ids = fetch
+        .ref_map()
+        .mappings
+        .iter()
+        .filter_map(|mapping| mapping.remote.peeled_id())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    // the remote would wait for wants which will never be sent
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    if !supports_filter(&fetch.ref_map().handshake.capabilities) {
+        fetch
+            .receive(gix::progress::Discard, &false.into())
+            .map_err(|e| errors::FetchError::Read(Box::new(repo_url.clone()), Box::new(e)))?;
+
+        return Ok(ids);
+    }
+
+    let mappings = fetch
+        .ref_map()
+        .mappings
+        .iter()
+        .filter_map(|mapping| Some((mapping.remote.as_id()?.to_owned(), mapping.local.clone())))
+        .collect::<Vec<_>>();
+    drop(fetch);
+
+    // objects which are already here don't have to be fetched again, unless their history is missing
+    let wants = mappings
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| matches!(shallow, Shallow::DepthAtRemote(_)) || !repo.has_object(id))
+        .collect::<HashSet<_>>();
+
+    if !wants.is_empty() {
+        fetch_objects(
+            repo,
+            repo_url,
+            auth_config,
+            wants,
+            shallow,
+            Some(BLOBLESS_FILTER),
+        )?;
+    }
+
+    for (id, local) in mappings {
+        let Some(local) = local else {
+            continue;
+        };
+
+        repo.reference(
+            local.to_str_lossy().as_ref(),
+            id,
+            gix::refs::transaction::PreviousValue::Any,
+            "fetch",
+        )
+        .map_err(|e| errors::FetchError::UpdateRef(Box::new(repo_url.clone()), Box::new(e)))?;
+    }
+
+    Ok(ids)
+}
+
+/// The filter used to leave blobs out of fetches
+const BLOBLESS_FILTER: &str = "blob:none";
+
+/// Whether the remote supports filtering the objects it sends
+fn supports_filter(capabilities: &gix::protocol::transport::client::Capabilities) -> bool {
+    capabilities
+        .capability("fetch")
+        .and_then(|fetch| fetch.supports("filter"))
+        .unwrap_or_else(|| capabilities.contains("filter"))
+}
+
+/// Fetches the objects in `wants` along with the objects they reference, without negotiating
+/// what the repository already has. `gix` can't send filters yet, so the protocol is used directly
+fn fetch_objects(
+    repo: &gix::Repository,
+    repo_url: &gix::Url,
+    auth_config: &AuthConfig,
+    wants: impl IntoIterator<Item = ObjectId>,
+    shallow: Shallow,
+    filter: Option<&str>,
+) -> Result<(), errors::FetchError> {
+    let remote = repo
+        .remote_at(repo_url.clone())
+        .map_err(|e| errors::FetchError::Remote(Box::new(repo_url.clone()), Box::new(e)))?;
+
+    let mut connection = remote
+        .connect(Direction::Fetch)
+        .map_err(|e| errors::FetchError::Connect(Box::new(repo_url.clone()), Box::new(e)))?;
+
+    let authenticate = match credentials_for(repo_url, auth_config) {
+        Some(credentials) => credentials,
+        None => connection
+            .configured_credentials(repo_url.clone())
+            .map_err(|e| {
+                errors::FetchError::Credentials(Box::new(repo_url.clone()), Box::new(e))
+            })?,
+    };
+
+    if let Some(options) = repo
+        .transport_options(repo_url.to_bstring().as_bstr(), None)
+        .map_err(|e| {
+            errors::FetchError::TransportOptions(Box::new(repo_url.clone()), Box::new(e))
+        })?
+    {
+        connection
+            .transport_mut()
+            .configure(&*options)
+            .map_err(|e| errors::FetchError::ConfigureTransport(Box::new(repo_url.clone()), e))?;
+    }
+
+    let shallow_lock = match shallow {
+        Shallow::NoChange => None,
+        _ => Some(
+            gix::lock::File::acquire_to_update_resource(
+                repo.shallow_file(),
+                gix::lock::acquire::Fail::Immediately,
+                None,
+            )
+            .map_err(|e| errors::FetchError::LockShallow(Box::new(repo_url.clone()), e))?,
+        ),
+    };
+    let shallow_commits = repo
+        .shallow_commits()
+        .map_err(|e| errors::FetchError::ReadShallow(Box::new(repo_url.clone()), e))?;
+
+    let mut delegate = FetchObjects {
+        repo,
+        wants: wants.into_iter().collect(),
+        shallow_commits: shallow_commits
+            .as_ref()
+            .map(|commits| commits.to_vec())
+            .unwrap_or_default(),
+        depth: match shallow {
+            Shallow::DepthAtRemote(depth) => Some(depth.get() as usize),
+            _ => None,
+        },
+        filter,
+        shallow_updates: vec![],
+    };
+
+    gix::protocol::fetch(
+        connection.transport_mut(),
+        &mut delegate,
+        authenticate,
+        gix::progress::Discard,
+        gix::protocol::FetchConnection::TerminateOnSuccessfulCompletion,
+        gix::env::agent(),
+        false,
+    )
+    .map_err(|e| errors::FetchError::FetchObjects(Box::new(repo_url.clone()), Box::new(e)))?;
+
+    if let Some(shallow_lock) = shallow_lock {
+        if !delegate.shallow_updates.is_empty() {
+            gix::shallow::write(shallow_lock, shallow_commits, &delegate.shallow_updates)
+                .map_err(|e| errors::FetchError::WriteShallow(Box::new(repo_url.clone()), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks for a fixed set of objects in a single round, and writes the pack it receives into the repository
+struct FetchObjects<'a> {
+    repo: &'a gix::Repository,
+    wants: Vec<ObjectId>,
+    shallow_commits: Vec<ObjectId>,
+    depth: Option<usize>,
+    filter: Option<&'a str>,
+    shallow_updates: Vec<gix::protocol::fetch::response::ShallowUpdate>,
+}
+
+impl gix::protocol::fetch::DelegateBlocking for FetchObjects<'_> {
+    fn prepare_ls_refs(
+        &mut self,
+        _server: &gix::protocol::transport::client::Capabilities,
+        _arguments: &mut Vec<gix::bstr::BString>,
+        _features: &mut Vec<(&str, Option<std::borrow::Cow<'_, str>>)>,
+    ) -> std::io::Result<gix::protocol::ls_refs::Action> {
+        // the wanted objects are known already
+        Ok(gix::protocol::ls_refs::Action::Skip)
+    }
+
+    fn negotiate(
+        &mut self,
+        _refs: &[gix::protocol::handshake::Ref],
+        arguments: &mut gix::protocol::fetch::Arguments,
+        _previous_response: Option<&gix::protocol::fetch::Response>,
+    ) -> std::io::Result<gix::protocol::fetch::Action> {
+        for id in &self.wants {
+            arguments.want(id);
+        }
+
+        if let Some(depth) = self.depth {
+            if !arguments.can_use_shallow() {
+                return Err(std::io::Error::other(
+                    "the remote doesn't support shallow fetches",
+                ));
+            }
+
+            for id in &self.shallow_commits {
+                arguments.shallow(id);
+            }
+            arguments.deepen(depth);
+        }
+
+        if let Some(filter) = self.filter {
+            if !arguments.can_use_filter() {
+                return Err(std::io::Error::other("the remote doesn't support filters"));
+            }
+
+            arguments.filter(filter);
+        }
+
+        // there are no haves to negotiate, so the remote can send the pack right away
+        Ok(gix::protocol::fetch::Action::Cancel)
+    }
+}
+
+impl gix::protocol::fetch::Delegate for FetchObjects<'_> {
+    fn receive_pack(
+        &mut self,
+        mut input: impl std::io::BufRead,
+        mut progress: impl gix::NestedProgress + 'static,
+        _refs: &[gix::protocol::handshake::Ref],
+        previous_response: &gix::protocol::fetch::Response,
+    ) -> std::io::Result<()> {
+        self.shallow_updates = previous_response.shallow_updates().to_vec();
+
+        let outcome = gix::odb::pack::Bundle::write_to_directory(
+            &mut input,
+            Some(&self.repo.objects.store_ref().path().join("pack")),
+            &mut progress,
+            &false.into(),
+            Some(self.repo.objects.clone()),
+            gix::odb::pack::bundle::write::Options {
+                thread_limit: None,
+                index_version: Default::default(),
+                iteration_mode: gix::odb::pack::data::input::Mode::Verify,
+                object_hash: self.repo.object_hash(),
+            },
+        )
+        .map_err(std::io::Error::other)?;
+
+        // these repositories are never garbage collected, so the pack doesn't have to be kept
+        if let Some(keep_path) = outcome.keep_path {
+            std::fs::remove_file(keep_path)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl PackageSource for GitPackageSource {
@@ -71,7 +789,24 @@ impl PackageSource for GitPackageSource {
     type DownloadError = errors::DownloadError;
 
     async fn refresh(&self, project: &Project) -> Result<(), Self::RefreshError> {
-        GitBasedSource::refresh(self, project).await
+        // revisions are fetched when they're needed, so only the repository has to exist
+        let path = self.path(project);
+        let _lock = project.lock_repo(&path).await?;
+        if path.exists() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&path).await?;
+
+        spawn_blocking(move || match gix::init_bare(&path) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(crate::source::git_index::errors::RefreshError::Init(
+                path,
+                Box::new(e),
+            )),
+        })
+        .await
+        .unwrap()
     }
 
     async fn resolve(
@@ -81,31 +816,87 @@ impl PackageSource for GitPackageSource {
         _project_target: TargetKind,
         _refreshed_sources: &mut HashSet<PackageSources>,
     ) -> Result<ResolveResult<Self::Ref>, Self::ResolveError> {
-        let repo = gix::open(self.path(project))
+        let fetched_id = self.fetch_rev(project, Some(&specifier.rev)).await?;
+
+        let commit_id = match fetched_id {
+            Some(id) => id,
+            None => {
+                let repo = gix::open_opts(self.path(project), self.open_options(project)).map_err(
+                    |e| errors::ResolveError::OpenRepo(Box::new(self.repo_url.clone()), e),
+                )?;
+
+                repo.rev_parse_single(BStr::new(&specifier.rev))
+                    .map_err(|e| {
+                        errors::ResolveError::ParseRev(
+                            specifier.rev.clone(),
+                            Box::new(self.repo_url.clone()),
+                            e,
+                        )
+                    })?
+                    .detach()
+            }
+        };
+
+        // blob-less fetches leave out the files read below
+        let path = specifier
+            .path
+            .as_ref()
+            .map(|path| path.normalize())
+            .unwrap_or_default();
+        #[allow(unused_mut)]
+        let mut paths = vec![
+            path.join(MANIFEST_FILE_NAME),
+            RelativePathBuf::from(LOCKFILE_FILE_NAME),
+            RelativePathBuf::from(".gitmodules"),
+        ];
+        #[cfg(feature = "wally-compat")]
+        paths.push(path.join(crate::source::wally::compat_util::WALLY_MANIFEST_FILE_NAME));
+        self.fetch_blobs_at(project, commit_id, &paths).await?;
+
+        let repo = gix::open_opts(self.path(project), self.open_options(project))
             .map_err(|e| errors::ResolveError::OpenRepo(Box::new(self.repo_url.clone()), e))?;
-        let rev = repo
-            .rev_parse_single(BStr::new(&specifier.rev))
-            .map_err(|e| {
-                errors::ResolveError::ParseRev(
-                    specifier.rev.clone(),
-                    Box::new(self.repo_url.clone()),
-                    e,
-                )
-            })?;
+        let rev = commit_id.attach(&repo);
 
         // TODO: possibly use the search algorithm from src/main.rs to find the workspace root
 
-        let root_tree = rev
+        let commit = rev
             .object()
             .map_err(|e| {
                 errors::ResolveError::ParseRevToObject(Box::new(self.repo_url.clone()), e)
             })?
-            .peel_to_tree()
+            .peel_to_commit()
             .map_err(|e| {
-                errors::ResolveError::ParseObjectToTree(Box::new(self.repo_url.clone()), e)
+                errors::ResolveError::ParseObjectToCommit(Box::new(self.repo_url.clone()), e)
             })?;
+        let root_tree = commit.tree().map_err(|e| {
+            errors::ResolveError::ParseCommitToTree(Box::new(self.repo_url.clone()), e)
+        })?;
 
         let tree = if let Some(path) = &specifier.path {
+            // the contents of submodules aren't part of the repository's tree
+            let mut prefix = RelativePathBuf::new();
+            for component in path.normalize().iter() {
+                prefix.push(component);
+
+                let entry = root_tree
+                    .lookup_entry_by_path(prefix.as_str())
+                    .map_err(|e| {
+                        errors::ResolveError::ReadTreeEntry(
+                            Box::new(self.repo_url.clone()),
+                            prefix.clone(),
+                            e,
+                        )
+                    })?;
+
+                if entry.is_some_and(|entry| entry.mode().is_commit()) {
+                    return Err(errors::ResolveError::PathInSubmodule(
+                        Box::new(self.repo_url.clone()),
+                        path.clone(),
+                        prefix,
+                    ));
+                }
+            }
+
             root_tree
                 .lookup_entry_by_path(path.as_str())
                 .map_err(|e| {
@@ -244,7 +1035,7 @@ impl PackageSource for GitPackageSource {
 
                                 spec = DependencySpecifiers::Git(GitDependencySpecifier {
                                     repo: self.repo_url.clone(),
-                                    rev: rev.to_string(),
+                                    rev: commit.id.to_string(),
                                     path: Some(path),
                                 })
                             }
@@ -314,6 +1105,12 @@ impl PackageSource for GitPackageSource {
         };
 
         let new_structure = matches!(name, PackageNames::Pesde(_));
+        let tree_id = tree.id.to_string();
+        let commit_id = commit.id;
+
+        let submodules = self
+            .resolve_submodules(project, commit_id, specifier.path.clone())
+            .await?;
 
         Ok((
             name,
@@ -321,7 +1118,9 @@ impl PackageSource for GitPackageSource {
                 version_id,
                 GitPackageRef {
                     repo: self.repo_url.clone(),
-                    tree_id: tree.id.to_string(),
+                    tree_id,
+                    commit_id: Some(commit_id.to_string()),
+                    submodules,
                     new_structure,
                     dependencies,
                 },
@@ -334,12 +1133,9 @@ impl PackageSource for GitPackageSource {
         pkg_ref: &Self::Ref,
         project: &Project,
         _reqwest: &reqwest::Client,
+        _reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError> {
-        let index_file = project
-            .cas_dir
-            .join("git_index")
-            .join(hash(self.as_bytes()))
-            .join(&pkg_ref.tree_id);
+        let index_file = pkg_ref.cas_index_path(project.cas_dir());
 
         match fs::read_to_string(&index_file).await {
             Ok(s) => {
@@ -381,8 +1177,12 @@ impl PackageSource for GitPackageSource {
                     #[cfg(feature = "wally-compat")]
                     None if !pkg_ref.new_structure => {
                         let tempdir = tempfile::tempdir()?;
-                        fs.write_to(tempdir.path(), project.cas_dir(), false)
-                            .await?;
+                        fs.write_to(
+                            tempdir.path(),
+                            project.cas_dir(),
+                            crate::source::fs::LinkMode::Copy,
+                        )
+                        .await?;
 
                         crate::source::wally::compat_util::get_target(project, &tempdir).await?
                     }
@@ -399,78 +1199,45 @@ impl PackageSource for GitPackageSource {
             Err(e) => return Err(errors::DownloadError::Io(e)),
         }
 
-        let repo = gix::open(self.path(project))
-            .map_err(|e| errors::DownloadError::OpenRepo(Box::new(self.repo_url.clone()), e))?
-            .into_sync();
-        let repo_url = self.repo_url.clone();
-        let tree_id = pkg_ref.tree_id.clone();
-
-        let (repo, records) = spawn_blocking(move || {
-            let repo = repo.to_thread_local();
-
-            let mut recorder = Recorder::default();
-
-            {
-                let object_id = match tree_id.parse::<ObjectId>() {
-                    Ok(oid) => oid,
-                    Err(e) => {
-                        return Err(errors::DownloadError::ParseTreeId(Box::new(repo_url), e))
-                    }
-                };
-                let object = match repo.find_object(object_id) {
-                    Ok(object) => object,
-                    Err(e) => {
-                        return Err(errors::DownloadError::ParseOidToObject(
-                            object_id,
-                            Box::new(repo_url),
-                            e,
-                        ))
-                    }
-                };
-
-                let tree = match object.peel_to_tree() {
-                    Ok(tree) => tree,
-                    Err(e) => {
-                        return Err(errors::DownloadError::ParseObjectToTree(
-                            Box::new(repo_url),
-                            e,
-                        ))
-                    }
-                };
-
-                if let Err(e) = tree.traverse().breadthfirst(&mut recorder) {
-                    return Err(errors::DownloadError::TraverseTree(Box::new(repo_url), e));
-                }
-            }
-
-            Ok::<_, errors::DownloadError>((repo.into_sync(), recorder.records))
-        })
-        .await
-        .unwrap()?;
+        let tree_id = pkg_ref
+            .tree_id
+            .parse::<ObjectId>()
+            .map_err(|e| errors::DownloadError::ParseTreeId(Box::new(self.repo_url.clone()), e))?;
 
-        let repo = repo.to_thread_local();
+        let repo = gix::open_opts(self.path(project), self.open_options(project))
+            .map_err(|e| errors::DownloadError::OpenRepo(Box::new(self.repo_url.clone()), e))?;
 
-        let records = records
-            .into_iter()
-            .map(|entry| {
-                let object = repo.find_object(entry.oid).map_err(|e| {
-                    errors::DownloadError::ParseOidToObject(
-                        entry.oid,
-                        Box::new(self.repo_url.clone()),
-                        e,
-                    )
-                })?;
+        if !repo.has_object(tree_id) {
+            // lockfiles from older versions don't contain the commit, so the whole repository is fetched
+            self.fetch_rev(project, pkg_ref.commit_id.as_deref())
+                .await?;
+        }
 
-                Ok::<_, errors::DownloadError>((
-                    RelativePathBuf::from(entry.filepath.to_string()),
-                    if matches!(object.kind, gix::object::Kind::Tree) {
-                        None
-                    } else {
-                        Some(object.data.clone())
-                    },
-                ))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let mut records = self.read_tree(project, tree_id).await?;
+
+        for (path, submodule) in &pkg_ref.submodules {
+            let commit_id = submodule
+                .commit_id
+                .parse::<ObjectId>()
+                .map_err(|e| errors::DownloadError::ParseSubmoduleCommitId(path.clone(), e))?;
+
+            let source = GitPackageSource::new(submodule.repo.clone());
+            PackageSource::refresh(&source, project)
+                .await
+                .map_err(|e| errors::DownloadError::RefreshSubmodule(path.clone(), Box::new(e)))?;
+            source
+                .fetch_rev(project, Some(&submodule.commit_id))
+                .await?;
+
+            records.push((path.clone(), None));
+            records.extend(
+                source
+                    .read_tree(project, commit_id)
+                    .await?
+                    .into_iter()
+                    .map(|(file_path, contents)| (path.join(file_path), contents)),
+            );
+        }
 
         let manifest = Arc::new(Mutex::new(None::<Vec<u8>>));
         let entries = try_join_all(
@@ -543,8 +1310,12 @@ impl PackageSource for GitPackageSource {
             #[cfg(feature = "wally-compat")]
             None if !pkg_ref.new_structure => {
                 let tempdir = tempfile::tempdir()?;
-                fs.write_to(tempdir.path(), project.cas_dir(), false)
-                    .await?;
+                fs.write_to(
+                    tempdir.path(),
+                    project.cas_dir(),
+                    crate::source::fs::LinkMode::Copy,
+                )
+                .await?;
 
                 crate::source::wally::compat_util::get_target(project, &tempdir).await?
             }
@@ -559,7 +1330,8 @@ impl PackageSource for GitPackageSource {
             fs::create_dir_all(parent).await?;
         }
 
-        fs::write(
+        write_atomically(
+            project.cas_dir(),
             &index_file,
             toml::to_string(&fs).map_err(|e| {
                 errors::DownloadError::SerializeIndex(Box::new(self.repo_url.clone()), e)
@@ -603,6 +1375,52 @@ pub mod errors {
         #[error("error parsing object to tree for repository {0}")]
         ParseObjectToTree(Box<gix::Url>, #[source] gix::object::peel::to_kind::Error),
 
+        /// An error occurred parsing object to commit
+        #[error("error parsing object to commit for repository {0}")]
+        ParseObjectToCommit(Box<gix::Url>, #[source] gix::object::peel::to_kind::Error),
+
+        /// An error occurred getting the tree of a commit
+        #[error("error getting tree of commit for repository {0}")]
+        ParseCommitToTree(Box<gix::Url>, #[source] gix::object::commit::Error),
+
+        /// An error occurred fetching the revision
+        #[error("error fetching revision")]
+        Fetch(#[from] FetchError),
+
+        /// An error occurred reading the `.gitmodules` file
+        #[error("error reading .gitmodules of repository {0}")]
+        ReadGitmodules(
+            Box<gix::Url>,
+            #[source] crate::source::git_index::errors::ReadFile,
+        ),
+
+        /// An error occurred parsing the `.gitmodules` file
+        #[error("error parsing .gitmodules of repository {0}")]
+        ParseGitmodules(Box<gix::Url>, #[source] gix::config::parse::Error),
+
+        /// A submodule has an invalid path
+        #[error("invalid submodule path in repository {0}")]
+        SubmodulePath(Box<gix::Url>, #[source] gix::submodule::config::path::Error),
+
+        /// A submodule has no URL
+        #[error("no url found for submodule {0} in repository {1}")]
+        NoSubmoduleUrl(String, Box<gix::Url>),
+
+        /// An error occurred parsing a submodule's URL
+        #[error("error parsing url of submodule {0} in repository {1}")]
+        ParseSubmoduleUrl(String, Box<gix::Url>, #[source] gix::url::parse::Error),
+
+        /// The package path is inside a submodule
+        #[error("path {1} in repository {0} is inside the submodule at {2}, depend on the submodule's repository instead")]
+        PathInSubmodule(Box<gix::Url>, RelativePathBuf, RelativePathBuf),
+
+        /// An error occurred refreshing a submodule's repository
+        #[error("error refreshing submodule repository {0}")]
+        RefreshSubmodule(
+            Box<gix::Url>,
+            #[source] Box<crate::source::git_index::errors::RefreshError>,
+        ),
+
         /// An error occurred reading the manifest
         #[error("error reading manifest of repository {0}")]
         ReadManifest(
@@ -724,5 +1542,94 @@ pub mod errors {
         /// An error occurred while parsing tree_id to ObjectId
         #[error("error parsing tree_id to ObjectId for repository {0}")]
         ParseTreeId(Box<gix::Url>, #[source] gix::hash::decode::Error),
+
+        /// An error occurred fetching the package's tree
+        #[error("error fetching package tree")]
+        Fetch(#[from] FetchError),
+
+        /// An error occurred parsing a submodule's commit id
+        #[error("error parsing commit id of submodule at {0}")]
+        ParseSubmoduleCommitId(RelativePathBuf, #[source] gix::hash::decode::Error),
+
+        /// An error occurred refreshing a submodule's repository
+        #[error("error refreshing repository of submodule at {0}")]
+        RefreshSubmodule(
+            RelativePathBuf,
+            #[source] Box<crate::source::git_index::errors::RefreshError>,
+        ),
+    }
+
+    /// Errors that can occur when fetching from a Git package source
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum FetchError {
+        /// An error occurred locking the Git repository
+        #[error("error locking Git repository")]
+        Lock(#[from] crate::locks::errors::LockError),
+
+        /// An error occurred opening the Git repository
+        #[error("error opening Git repository for url {0}")]
+        OpenRepo(Box<gix::Url>, #[source] Box<gix::open::Error>),
+
+        /// An error occurred creating the remote
+        #[error("error creating remote for repository {0}")]
+        Remote(Box<gix::Url>, #[source] Box<gix::remote::init::Error>),
+
+        /// An error occurred parsing a refspec
+        #[error("error parsing refspec for repository {0}")]
+        RefSpec(Box<gix::Url>, #[source] Box<gix::refspec::parse::Error>),
+
+        /// An error occurred connecting to the remote repository
+        #[error("error connecting to remote repository {0}")]
+        Connect(Box<gix::Url>, #[source] Box<gix::remote::connect::Error>),
+
+        /// An error occurred preparing to fetch from the remote repository
+        #[error("error preparing fetch from remote repository {0}")]
+        PrepareFetch(
+            Box<gix::Url>,
+            #[source] Box<gix::remote::fetch::prepare::Error>,
+        ),
+
+        /// An error occurred reading from the remote repository
+        #[error("error reading from remote repository {0}")]
+        Read(Box<gix::Url>, #[source] Box<gix::remote::fetch::Error>),
+
+        /// An error occurred getting the credentials for the remote repository
+        #[error("error getting credentials for remote repository {0}")]
+        Credentials(
+            Box<gix::Url>,
+            #[source] Box<gix::config::credential_helpers::Error>,
+        ),
+
+        /// An error occurred getting the transport options for the remote repository
+        #[error("error getting transport options for remote repository {0}")]
+        TransportOptions(Box<gix::Url>, #[source] Box<gix::config::transport::Error>),
+
+        /// An error occurred configuring the transport for the remote repository
+        #[error("error configuring transport for remote repository {0}")]
+        ConfigureTransport(
+            Box<gix::Url>,
+            #[source] Box<dyn std::error::Error + Send + Sync + 'static>,
+        ),
+
+        /// An error occurred locking the shallow file of the repository
+        #[error("error locking shallow file of repository {0}")]
+        LockShallow(Box<gix::Url>, #[source] gix::lock::acquire::Error),
+
+        /// An error occurred reading the shallow commits of the repository
+        #[error("error reading shallow commits of repository {0}")]
+        ReadShallow(Box<gix::Url>, #[source] gix::shallow::open::Error),
+
+        /// An error occurred writing the shallow commits of the repository
+        #[error("error writing shallow commits of repository {0}")]
+        WriteShallow(Box<gix::Url>, #[source] gix::shallow::write::Error),
+
+        /// An error occurred fetching objects from the remote repository
+        #[error("error fetching objects from remote repository {0}")]
+        FetchObjects(Box<gix::Url>, #[source] Box<gix::protocol::fetch::Error>),
+
+        /// An error occurred updating a reference of the repository
+        #[error("error updating reference of repository {0}")]
+        UpdateRef(Box<gix::Url>, #[source] Box<gix::reference::edit::Error>),
     }
 }
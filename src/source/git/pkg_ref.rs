@@ -1,9 +1,14 @@
+use relative_path::RelativePathBuf;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     manifest::DependencyType,
     source::{git::GitPackageSource, DependencySpecifiers, PackageRef, PackageSources},
+    util::hash,
 };
 
 /// A Git package reference
@@ -17,12 +22,53 @@ pub struct GitPackageRef {
     pub repo: gix::Url,
     /// The id of the package's tree
     pub tree_id: String,
+    /// The id of the commit the package's tree was resolved from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+    /// The submodules of the package (including nested ones), by their path in the package
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub submodules: BTreeMap<RelativePathBuf, GitSubmodule>,
     /// The dependencies of the package
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub dependencies: BTreeMap<String, (DependencySpecifiers, DependencyType)>,
     /// Whether this package uses the new structure
     pub new_structure: bool,
 }
+
+/// A Git submodule of a package
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct GitSubmodule {
+    /// The repository of the submodule
+    #[serde(
+        serialize_with = "crate::util::serialize_gix_url",
+        deserialize_with = "crate::util::deserialize_gix_url"
+    )]
+    pub repo: gix::Url,
+    /// The id of the submodule's commit
+    pub commit_id: String,
+}
+
+impl GitPackageRef {
+    /// The path of the cached file system of this package in the CAS
+    pub(crate) fn cas_index_path(&self, cas_dir: &Path) -> PathBuf {
+        let dir = cas_dir.join("git_index").join(hash(self.repo.to_bstring()));
+
+        if self.submodules.is_empty() {
+            return dir.join(&self.tree_id);
+        }
+
+        // caches from before submodules were included don't contain their contents
+        let submodules = self
+            .submodules
+            .iter()
+            .map(|(path, submodule)| format!("{path}:{}", submodule.commit_id))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        dir.join(format!("{}-{}", self.tree_id, hash(submodules)))
+    }
+}
+
 impl PackageRef for GitPackageRef {
     fn dependencies(&self) -> &BTreeMap<String, (DependencySpecifiers, DependencyType)> {
         &self.dependencies
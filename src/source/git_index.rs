@@ -1,5 +1,8 @@
 #![allow(async_fn_in_trait)]
-use crate::{util::authenticate_conn, Project};
+use crate::{
+    util::{authenticate_conn, git_config_overrides},
+    Project,
+};
 use fs_err::tokio as fs;
 use gix::remote::Direction;
 use tokio::task::spawn_blocking;
@@ -17,10 +20,16 @@ pub trait GitBasedSource {
         let path = self.path(project);
         let repo_url = self.repo_url().clone();
         let auth_config = project.auth_config.clone();
+        let config_overrides = git_config_overrides(&repo_url, project);
+
+        let _lock = project.lock_repo(&path).await?;
 
         if path.exists() {
             spawn_blocking(move || {
-                let repo = match gix::open(&path) {
+                let repo = match gix::open_opts(
+                    &path,
+                    gix::open::Options::default().config_overrides(config_overrides),
+                ) {
                     Ok(repo) => repo,
                     Err(e) => return Err(errors::RefreshError::Open(path, Box::new(e))),
                 };
@@ -76,6 +85,7 @@ pub trait GitBasedSource {
         spawn_blocking(move || {
             gix::prepare_clone_bare(repo_url.clone(), &path)
                 .map_err(|e| errors::RefreshError::Clone(repo_url.to_string(), Box::new(e)))?
+                .with_in_memory_config_overrides(config_overrides)
                 .configure_connection(move |c| {
                     authenticate_conn(c, &auth_config);
                     Ok(())
@@ -119,8 +129,7 @@ pub fn read_file<I: IntoIterator<Item = P> + Clone, P: ToString + PartialEq<gix:
     Ok(Some(string))
 }
 
-/// Gets the root tree of a repository
-pub fn root_tree(repo: &gix::Repository) -> Result<gix::Tree, errors::TreeError> {
+fn root_object(repo: &gix::Repository) -> Result<gix::Object<'_>, errors::TreeError> {
     // this is a bare repo, so this is the actual path
     let path = repo.path().to_path_buf();
 
@@ -156,11 +165,16 @@ pub fn root_tree(repo: &gix::Repository) -> Result<gix::Tree, errors::TreeError>
         Err(e) => return Err(errors::TreeError::CannotPeel(reference_name, e)),
     };
 
-    let id_str = id.to_string();
-    let object = match id.object() {
-        Ok(object) => object,
-        Err(e) => return Err(errors::TreeError::CannotConvertToObject(id_str, e)),
-    };
+    match id.object() {
+        Ok(object) => Ok(object),
+        Err(e) => Err(errors::TreeError::CannotConvertToObject(id.to_string(), e)),
+    }
+}
+
+/// Gets the root tree of a repository
+pub fn root_tree(repo: &gix::Repository) -> Result<gix::Tree<'_>, errors::TreeError> {
+    let object = root_object(repo)?;
+    let id_str = object.id.to_string();
 
     match object.peel_to_tree() {
         Ok(tree) => Ok(tree),
@@ -168,6 +182,17 @@ pub fn root_tree(repo: &gix::Repository) -> Result<gix::Tree, errors::TreeError>
     }
 }
 
+/// Gets the commit the root tree of a repository belongs to
+pub fn root_commit(repo: &gix::Repository) -> Result<gix::Commit<'_>, errors::TreeError> {
+    let object = root_object(repo)?;
+    let id_str = object.id.to_string();
+
+    match object.peel_to_commit() {
+        Ok(commit) => Ok(commit),
+        Err(e) => Err(errors::TreeError::CannotPeelToCommit(id_str, e)),
+    }
+}
+
 /// Errors that can occur when interacting with a git-based package source
 pub mod errors {
     use std::path::PathBuf;
@@ -182,10 +207,18 @@ pub mod errors {
         #[error("error interacting with the filesystem")]
         Io(#[from] std::io::Error),
 
+        /// Error locking the repository
+        #[error("error locking repository")]
+        Lock(#[from] crate::locks::errors::LockError),
+
         /// Error opening the repository
         #[error("error opening repository at {0}")]
         Open(PathBuf, #[source] Box<gix::open::Error>),
 
+        /// Error initializing the repository
+        #[error("error initializing repository at {0}")]
+        Init(PathBuf, #[source] Box<gix::init::Error>),
+
         /// No default remote found in repository
         #[error("no default remote found in repository at {0}")]
         NoDefaultRemote(PathBuf),
@@ -254,6 +287,10 @@ pub mod errors {
         /// Error peeling object to tree in repository
         #[error("error peeling object {0} to tree")]
         CannotPeelToTree(String, #[source] gix::object::peel::to_kind::Error),
+
+        /// Error peeling object to commit in repository
+        #[error("error peeling object {0} to commit")]
+        CannotPeelToCommit(String, #[source] gix::object::peel::to_kind::Error),
     }
 
     /// Errors that can occur when reading a file from a git-based package source
@@ -1,4 +1,5 @@
 use crate::{
+    download::DownloadProgressReporter,
     manifest::target::{Target, TargetKind},
     names::PackageNames,
     source::{
@@ -10,6 +11,7 @@ use crate::{
 use std::{
     collections::{BTreeMap, HashSet},
     fmt::Debug,
+    sync::Arc,
 };
 
 /// Packages' filesystems
@@ -152,26 +154,27 @@ impl PackageSource for PackageSources {
         pkg_ref: &Self::Ref,
         project: &Project,
         reqwest: &reqwest::Client,
+        reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError> {
         match (self, pkg_ref) {
             (PackageSources::Pesde(source), PackageRefs::Pesde(pkg_ref)) => source
-                .download(pkg_ref, project, reqwest)
+                .download(pkg_ref, project, reqwest, reporter)
                 .await
                 .map_err(Into::into),
 
             #[cfg(feature = "wally-compat")]
             (PackageSources::Wally(source), PackageRefs::Wally(pkg_ref)) => source
-                .download(pkg_ref, project, reqwest)
+                .download(pkg_ref, project, reqwest, reporter)
                 .await
                 .map_err(Into::into),
 
             (PackageSources::Git(source), PackageRefs::Git(pkg_ref)) => source
-                .download(pkg_ref, project, reqwest)
+                .download(pkg_ref, project, reqwest, reporter)
                 .await
                 .map_err(Into::into),
 
             (PackageSources::Workspace(source), PackageRefs::Workspace(pkg_ref)) => source
-                .download(pkg_ref, project, reqwest)
+                .download(pkg_ref, project, reqwest, reporter)
                 .await
                 .map_err(Into::into),
 
@@ -188,6 +191,10 @@ pub mod errors {
     #[derive(Debug, Error)]
     #[non_exhaustive]
     pub enum RefreshError {
+        /// A pesde package source failed to refresh
+        #[error("error refreshing pesde package source")]
+        Pesde(#[from] crate::source::pesde::errors::RefreshError),
+
         /// A git-based package source failed to refresh
         #[error("error refreshing pesde package source")]
         GitBased(#[from] crate::source::git_index::errors::RefreshError),
@@ -2,25 +2,31 @@ use gix::Url;
 use relative_path::RelativePathBuf;
 use reqwest::header::{ACCEPT, AUTHORIZATION};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
     collections::{BTreeMap, BTreeSet, HashSet},
     fmt::Debug,
     hash::Hash,
     path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 use pkg_ref::PesdePackageRef;
 use specifier::PesdeDependencySpecifier;
 
 use crate::{
+    download::{response_reader, send_with_retries, DownloadProgressReporter},
     manifest::{
         target::{Target, TargetKind},
         DependencyType,
     },
-    names::PackageNames,
+    names::{PackageName, PackageNames},
     source::{
-        fs::{store_in_cas, FSEntry, PackageFS},
-        git_index::{read_file, root_tree, GitBasedSource},
+        fs::{persist_in_cas, stage_in_cas, write_atomically, FSEntry, PackageFS},
+        git_index::{read_file, root_commit, root_tree, GitBasedSource},
         DependencySpecifiers, PackageSource, PackageSources, ResolveResult, VersionId,
         IGNORED_DIRS, IGNORED_FILES,
     },
@@ -33,6 +39,8 @@ use tokio::task::spawn_blocking;
 
 /// The pesde package reference
 pub mod pkg_ref;
+/// Index signing utilities
+pub mod signing;
 /// The pesde dependency specifier
 pub mod specifier;
 
@@ -72,6 +80,31 @@ impl PesdePackageSource {
         self.repo_url.to_bstring().to_vec()
     }
 
+    /// Reads the index file of a package, if it exists
+    pub fn read_index_file(
+        &self,
+        name: &PackageName,
+        project: &Project,
+    ) -> Result<Option<IndexFile>, errors::ReadIndexFileError> {
+        let (scope, name_part) = name.as_str();
+        let repo = gix::open(self.path(project)).map_err(Box::new)?;
+        let tree = root_tree(&repo).map_err(Box::new)?;
+        let string = match read_file(&tree, [scope, name_part]) {
+            Ok(Some(s)) => s,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                return Err(errors::ReadIndexFileError::Read(
+                    name.to_string(),
+                    Box::new(e),
+                ))
+            }
+        };
+
+        toml::from_str(&string)
+            .map(Some)
+            .map_err(|e| errors::ReadIndexFileError::Parse(name.to_string(), e))
+    }
+
     /// Reads the config file
     pub async fn config(&self, project: &Project) -> Result<IndexConfig, errors::ConfigError> {
         let repo_url = self.repo_url.clone();
@@ -90,17 +123,229 @@ impl PesdePackageSource {
         .await
         .unwrap()
     }
+
+    fn trusted_keys_path(&self, project: &Project) -> PathBuf {
+        project
+            .data_dir
+            .join("trusted_keys")
+            .join(hash(self.as_bytes()))
+    }
+
+    /// Verifies that the current index commit is signed by a trusted key, and that it descends
+    /// from the last verified commit so the index can't be rolled back.
+    /// The keys advertised by the index are trusted on first use, and are only re-pinned
+    /// once a commit signed by an already trusted key has been verified
+    pub async fn verify_signature(&self, project: &Project) -> Result<(), errors::RefreshError> {
+        let config = self.config(project).await.map_err(Box::new)?;
+        let trusted_keys_path = self.trusted_keys_path(project);
+
+        let trusted = match fs::read_to_string(&trusted_keys_path).await {
+            Ok(s) => toml::from_str::<signing::TrustedKeys>(&s)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(errors::RefreshError::Io(e)),
+        };
+
+        if trusted.keys.is_empty() && config.signing_keys.is_empty() {
+            return Ok(());
+        }
+
+        let keys = if trusted.keys.is_empty() {
+            config.signing_keys.clone()
+        } else {
+            trusted.keys.clone()
+        };
+
+        let path = self.path(project);
+        let repo_url = self.repo_url.to_bstring().to_string();
+        let last_head = trusted.head.clone();
+
+        let head = spawn_blocking(move || {
+            let repo = gix::open(&path).map_err(Box::new)?;
+            let commit = root_commit(&repo).map_err(Box::new)?;
+            let tree_id = commit.tree_id()?.to_string();
+            let parent_id = commit.parent_ids().next().map(|id| id.to_string());
+            let message = commit.message_raw()?.to_string();
+
+            let Some(signature) = signing::signature_from_message(&message) else {
+                return Err(errors::RefreshError::Unsigned(repo_url));
+            };
+
+            if !signing::verify(&keys, &tree_id, parent_id.as_deref(), signature) {
+                return Err(errors::RefreshError::InvalidSignature(repo_url));
+            }
+
+            let head = commit.id.to_string();
+
+            if let Some(last_head) = last_head.filter(|last_head| last_head != &head) {
+                let mut descends = false;
+
+                for info in commit.ancestors().all().map_err(Box::new)? {
+                    if info.map_err(Box::new)?.id.to_string() == last_head {
+                        descends = true;
+                        break;
+                    }
+                }
+
+                if !descends {
+                    return Err(errors::RefreshError::RolledBack(repo_url, last_head));
+                }
+            }
+
+            Ok(head)
+        })
+        .await
+        .unwrap()?;
+
+        let keys = if config.signing_keys.is_empty() {
+            trusted.keys.clone()
+        } else {
+            config.signing_keys
+        };
+
+        if keys != trusted.keys || trusted.head.as_ref() != Some(&head) {
+            if keys != trusted.keys {
+                log::debug!("pinning signing keys for {}", self.repo_url);
+            }
+
+            fs::create_dir_all(trusted_keys_path.parent().unwrap()).await?;
+            fs::write(
+                &trusted_keys_path,
+                toml::to_string(&signing::TrustedKeys {
+                    keys,
+                    head: Some(head),
+                })?,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PesdePackageSource {
+    async fn download_archive(
+        request: reqwest::RequestBuilder,
+        integrity: Option<&str>,
+        project: &Project,
+        max_size: u64,
+        reporter: Arc<dyn DownloadProgressReporter>,
+    ) -> Result<BTreeMap<RelativePathBuf, FSEntry>, errors::DownloadError> {
+        let response = send_with_retries(request, project.download_config().max_retries()).await?;
+        let url = response.url().to_string();
+
+        if response.content_length().is_some_and(|len| len > max_size) {
+            return Err(errors::DownloadError::ArchiveTooLarge(max_size));
+        }
+
+        let hasher = Arc::new(std::sync::Mutex::new(Sha256::new()));
+        let size = Arc::new(AtomicU64::new(0));
+
+        let reader = response_reader(response, {
+            let hasher = hasher.clone();
+            let size = size.clone();
+
+            move |chunk| {
+                let len = chunk.len() as u64;
+                if size.fetch_add(len, Ordering::Relaxed) + len > max_size {
+                    return Err(std::io::Error::other("archive exceeds the maximum size"));
+                }
+
+                hasher.lock().unwrap().update(chunk);
+                reporter.report_progress(len);
+
+                Ok(())
+            }
+        });
+
+        let unpack_error = |e: std::io::Error| {
+            if size.load(Ordering::Relaxed) > max_size {
+                errors::DownloadError::ArchiveTooLarge(max_size)
+            } else {
+                errors::DownloadError::Unpack(e)
+            }
+        };
+
+        let mut decoder = async_compression::tokio::bufread::GzipDecoder::new(reader);
+        let mut entries = BTreeMap::new();
+        // files are only moved into the CAS once the archive is known to be intact
+        let mut staged = vec![];
+
+        {
+            let mut archive = tokio_tar::Archive::new(&mut decoder);
+            let mut archive_entries = archive.entries().map_err(unpack_error)?;
+
+            while let Some(entry) = archive_entries
+                .next()
+                .await
+                .transpose()
+                .map_err(unpack_error)?
+            {
+                let path = RelativePathBuf::from_path(entry.path().map_err(unpack_error)?).unwrap();
+                let name = path.file_name().unwrap_or("");
+
+                if entry.header().entry_type().is_dir() {
+                    if IGNORED_DIRS.contains(&name) {
+                        continue;
+                    }
+
+                    entries.insert(path, FSEntry::Directory);
+
+                    continue;
+                }
+
+                if IGNORED_FILES.contains(&name) {
+                    continue;
+                }
+
+                let (temp_path, hash) =
+                    stage_in_cas(project.cas_dir(), entry, |_| async { Ok(()) })
+                        .await
+                        .map_err(|e| match unpack_error(e) {
+                            errors::DownloadError::Unpack(e) => errors::DownloadError::Store(e),
+                            e => e,
+                        })?;
+                staged.push((temp_path, hash.clone()));
+                entries.insert(path, FSEntry::File(hash));
+            }
+        }
+
+        // the archive must be read to its end for its hash to be complete
+        tokio::io::copy(&mut decoder, &mut tokio::io::sink())
+            .await
+            .map_err(unpack_error)?;
+        tokio::io::copy_buf(&mut decoder.into_inner(), &mut tokio::io::sink())
+            .await
+            .map_err(unpack_error)?;
+
+        if let Some(integrity) = integrity {
+            let hash = format!("{:x}", hasher.lock().unwrap().clone().finalize());
+            if !hash.eq_ignore_ascii_case(integrity) {
+                return Err(errors::DownloadError::Integrity(url));
+            }
+        }
+
+        for (temp_path, hash) in staged {
+            persist_in_cas(project.cas_dir(), temp_path, &hash)
+                .await
+                .map_err(errors::DownloadError::Store)?;
+        }
+
+        Ok(entries)
+    }
 }
 
 impl PackageSource for PesdePackageSource {
     type Specifier = PesdeDependencySpecifier;
     type Ref = PesdePackageRef;
-    type RefreshError = crate::source::git_index::errors::RefreshError;
+    type RefreshError = errors::RefreshError;
     type ResolveError = errors::ResolveError;
     type DownloadError = errors::DownloadError;
 
     async fn refresh(&self, project: &Project) -> Result<(), Self::RefreshError> {
-        GitBasedSource::refresh(self, project).await
+        GitBasedSource::refresh(self, project)
+            .await
+            .map_err(Box::new)?;
+        self.verify_signature(project).await
     }
 
     async fn resolve(
@@ -110,22 +355,9 @@ impl PackageSource for PesdePackageSource {
         project_target: TargetKind,
         _refreshed_sources: &mut HashSet<PackageSources>,
     ) -> Result<ResolveResult<Self::Ref>, Self::ResolveError> {
-        let (scope, name) = specifier.name.as_str();
-        let repo = gix::open(self.path(project)).map_err(Box::new)?;
-        let tree = root_tree(&repo).map_err(Box::new)?;
-        let string = match read_file(&tree, [scope, name]) {
-            Ok(Some(s)) => s,
-            Ok(None) => return Err(Self::ResolveError::NotFound(specifier.name.to_string())),
-            Err(e) => {
-                return Err(Self::ResolveError::Read(
-                    specifier.name.to_string(),
-                    Box::new(e),
-                ))
-            }
-        };
-
-        let entries: IndexFile = toml::from_str(&string)
-            .map_err(|e| Self::ResolveError::Parse(specifier.name.to_string(), e))?;
+        let entries = self
+            .read_index_file(&specifier.name, project)?
+            .ok_or_else(|| Self::ResolveError::NotFound(specifier.name.to_string()))?;
 
         log::debug!("{} has {} possible entries", specifier.name, entries.len());
 
@@ -133,8 +365,10 @@ impl PackageSource for PesdePackageSource {
             PackageNames::Pesde(specifier.name.clone()),
             entries
                 .into_iter()
-                .filter(|(VersionId(version, target), _)| {
-                    specifier.version.matches(version)
+                .filter(|(VersionId(version, target), entry)| {
+                    // locked yanked versions are added back by the resolver
+                    !entry.yanked
+                        && specifier.version.matches(version)
                         && specifier.target.unwrap_or(project_target) == *target
                 })
                 .map(|(id, entry)| {
@@ -148,6 +382,7 @@ impl PackageSource for PesdePackageSource {
                             index_url: self.repo_url.clone(),
                             dependencies: entry.dependencies,
                             target: entry.target,
+                            integrity: entry.integrity,
                         },
                     )
                 })
@@ -160,14 +395,9 @@ impl PackageSource for PesdePackageSource {
         pkg_ref: &Self::Ref,
         project: &Project,
         reqwest: &reqwest::Client,
+        reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError> {
-        let config = self.config(project).await.map_err(Box::new)?;
-        let index_file = project
-            .cas_dir
-            .join("index")
-            .join(pkg_ref.name.escaped())
-            .join(pkg_ref.version.to_string())
-            .join(pkg_ref.target.to_string());
+        let index_file = pkg_ref.cas_index_path(project.cas_dir());
 
         match fs::read_to_string(&index_file).await {
             Ok(s) => {
@@ -183,60 +413,80 @@ impl PackageSource for PesdePackageSource {
             Err(e) => return Err(errors::DownloadError::ReadIndex(e)),
         }
 
-        let url = config
-            .download()
-            .replace("{PACKAGE}", &pkg_ref.name.to_string().replace("/", "%2F"))
-            .replace("{PACKAGE_VERSION}", &pkg_ref.version.to_string())
-            .replace("{PACKAGE_TARGET}", &pkg_ref.target.to_string());
-
-        let mut request = reqwest.get(&url).header(ACCEPT, "application/octet-stream");
+        let config = self.config(project).await.map_err(Box::new)?;
 
-        if let Some(token) = project.auth_config.tokens().get(&self.repo_url) {
-            log::debug!("using token for {}", self.repo_url);
-            request = request.header(AUTHORIZATION, token);
+        // user-configured mirrors take precedence over the ones advertised by the index. the values
+        // are whether the URL is advertised by the index, and whether it is a mirror
+        let mut urls = project
+            .mirrors()
+            .get(&self.repo_url)
+            .into_iter()
+            .flatten()
+            .map(|url| (url.clone(), false, true))
+            .chain(std::iter::once((config.download(), true, false)))
+            .chain(
+                config
+                    .mirror_urls()
+                    .into_iter()
+                    .map(|url| (url, true, true)),
+            )
+            .collect::<Vec<_>>();
+
+        // without a hash to check archives against, mirrors could serve anything
+        if pkg_ref.integrity.is_none() && urls.iter().any(|(_, _, mirror)| *mirror) {
+            log::warn!(
+                "not downloading {}@{} {} from mirrors, as its index entry has no integrity hash",
+                pkg_ref.name,
+                pkg_ref.version,
+                pkg_ref.target
+            );
+            urls.retain(|(_, _, mirror)| !mirror);
         }
 
-        let response = request.send().await?.error_for_status()?;
-        let bytes = response.bytes().await?;
+        let mut entries = None;
+        let mut last_error = None;
 
-        let mut decoder = async_compression::tokio::bufread::GzipDecoder::new(bytes.as_ref());
-        let mut archive = tokio_tar::Archive::new(&mut decoder);
+        for (url, authenticated, _) in urls {
+            let url = url
+                .replace("{PACKAGE}", &pkg_ref.name.to_string().replace("/", "%2F"))
+                .replace("{PACKAGE_VERSION}", &pkg_ref.version.to_string())
+                .replace("{PACKAGE_TARGET}", &pkg_ref.target.to_string());
 
-        let mut entries = BTreeMap::new();
+            let mut request = reqwest.get(&url).header(ACCEPT, "application/octet-stream");
 
-        let mut archive_entries = archive.entries().map_err(errors::DownloadError::Unpack)?;
-
-        while let Some(entry) = archive_entries
-            .next()
-            .await
-            .transpose()
-            .map_err(errors::DownloadError::Unpack)?
-        {
-            let path =
-                RelativePathBuf::from_path(entry.path().map_err(errors::DownloadError::Unpack)?)
-                    .unwrap();
-            let name = path.file_name().unwrap_or("");
-
-            if entry.header().entry_type().is_dir() {
-                if IGNORED_DIRS.contains(&name) {
-                    continue;
+            // only send the token to URLs advertised by the index
+            if authenticated {
+                if let Some(token) = project.auth_config.tokens().get(&self.repo_url) {
+                    log::debug!("using token for {}", self.repo_url);
+                    request = request.header(AUTHORIZATION, token);
                 }
-
-                entries.insert(path, FSEntry::Directory);
-
-                continue;
             }
 
-            if IGNORED_FILES.contains(&name) {
-                continue;
+            match Self::download_archive(
+                request,
+                pkg_ref.integrity.as_deref(),
+                project,
+                config.max_archive_size as u64,
+                reporter.clone(),
+            )
+            .await
+            {
+                Ok(e) => {
+                    entries = Some(e);
+                    break;
+                }
+                Err(e) => {
+                    log::warn!("failed to download {} from {url}: {e}", pkg_ref.name);
+                    last_error = Some(e);
+                }
             }
-
-            let hash = store_in_cas(project.cas_dir(), entry, |_| async { Ok(()) })
-                .await
-                .map_err(errors::DownloadError::Store)?;
-            entries.insert(path, FSEntry::File(hash));
         }
 
+        let entries = match entries {
+            Some(entries) => entries,
+            None => return Err(last_error.unwrap()),
+        };
+
         let fs = PackageFS::CAS(entries);
 
         if let Some(parent) = index_file.parent() {
@@ -245,7 +495,7 @@ impl PackageSource for PesdePackageSource {
                 .map_err(errors::DownloadError::WriteIndex)?;
         }
 
-        fs::write(&index_file, toml::to_string(&fs)?)
+        write_atomically(project.cas_dir(), &index_file, toml::to_string(&fs)?)
             .await
             .map_err(errors::DownloadError::WriteIndex)?;
 
@@ -316,6 +566,15 @@ pub struct IndexConfig {
     /// The maximum size of an archive in bytes
     #[serde(default = "default_archive_size")]
     pub max_archive_size: usize,
+    /// The base64 encoded Ed25519 public keys which sign commits to this index
+    #[serde(default)]
+    pub signing_keys: BTreeSet<String>,
+    /// URLs to download packages from if the download URL fails, in order of preference
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// The URL of the Git repository containing security advisories for this index's packages
+    #[serde(default, deserialize_with = "crate::util::deserialize_gix_url_option")]
+    pub advisories: Option<gix::Url>,
 }
 
 impl IndexConfig {
@@ -331,6 +590,14 @@ impl IndexConfig {
             .unwrap_or("{API_URL}/v0/packages/{PACKAGE}/{PACKAGE_VERSION}/{PACKAGE_TARGET}")
             .replace("{API_URL}", self.api())
     }
+
+    /// The URLs of the mirrors to download packages from, in order of preference
+    pub fn mirror_urls(&self) -> Vec<String> {
+        self.mirrors
+            .iter()
+            .map(|mirror| mirror.replace("{API_URL}", self.api()))
+            .collect()
+    }
 }
 
 /// An entry in a package's documentation
@@ -415,6 +682,20 @@ pub struct IndexFileEntry {
     /// The dependencies of this package
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub dependencies: BTreeMap<String, (DependencySpecifiers, DependencyType)>,
+
+    /// The hex encoded SHA-256 hash of the package's archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+
+    /// Whether this version has been yanked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    /// The reason this version was yanked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yank_reason: Option<String>,
+    /// The deprecation message of this version, if it is deprecated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
 }
 
 /// The index file for a package
@@ -426,10 +707,67 @@ pub mod errors {
 
     use crate::source::git_index::errors::{ReadFile, TreeError};
 
-    /// Errors that can occur when resolving a package from a pesde package source
+    /// Errors that can occur when refreshing a pesde package source
     #[derive(Debug, Error)]
     #[non_exhaustive]
-    pub enum ResolveError {
+    pub enum RefreshError {
+        /// Error refreshing the index repository
+        #[error("error refreshing index repository")]
+        Refresh(#[from] Box<crate::source::git_index::errors::RefreshError>),
+
+        /// Error reading the config file
+        #[error("error reading config file")]
+        Config(#[from] Box<ConfigError>),
+
+        /// Error opening repository
+        #[error("error opening repository")]
+        Open(#[from] Box<gix::open::Error>),
+
+        /// Error getting commit
+        #[error("error getting commit")]
+        Commit(#[from] Box<TreeError>),
+
+        /// Error decoding commit
+        #[error("error decoding commit")]
+        Decode(#[from] gix::objs::decode::Error),
+
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error deserializing trusted keys
+        #[error("error deserializing trusted keys")]
+        DeserializeTrustedKeys(#[from] toml::de::Error),
+
+        /// Error serializing trusted keys
+        #[error("error serializing trusted keys")]
+        SerializeTrustedKeys(#[from] toml::ser::Error),
+
+        /// The index commit is not signed
+        #[error("index {0} is not signed")]
+        Unsigned(String),
+
+        /// The index commit is not signed by a trusted key
+        #[error("index {0} is not signed by a trusted key")]
+        InvalidSignature(String),
+
+        /// Error walking the index history
+        #[error("error walking index history")]
+        Walk(#[from] Box<gix::revision::walk::Error>),
+
+        /// Error iterating the index history
+        #[error("error iterating index history")]
+        WalkIter(#[from] Box<gix::revision::walk::iter::Error>),
+
+        /// The index commit doesn't descend from the last verified commit
+        #[error("index {0} doesn't descend from the last verified commit {1}, it may have been rolled back")]
+        RolledBack(String, String),
+    }
+
+    /// Errors that can occur when reading a package's index file from a pesde package source
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum ReadIndexFileError {
         /// Error opening repository
         #[error("error opening repository")]
         Open(#[from] Box<gix::open::Error>),
@@ -438,10 +776,6 @@ pub mod errors {
         #[error("error getting tree")]
         Tree(#[from] Box<TreeError>),
 
-        /// Package not found in index
-        #[error("package {0} not found")]
-        NotFound(String),
-
         /// Error reading file for package
         #[error("error reading file for {0}")]
         Read(String, #[source] Box<ReadFile>),
@@ -451,6 +785,19 @@ pub mod errors {
         Parse(String, #[source] toml::de::Error),
     }
 
+    /// Errors that can occur when resolving a package from a pesde package source
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum ResolveError {
+        /// Error reading the index file for package
+        #[error("error reading index file")]
+        ReadIndexFile(#[from] ReadIndexFileError),
+
+        /// Package not found in index
+        #[error("package {0} not found")]
+        NotFound(String),
+    }
+
     /// Errors that can occur when reading the config file for a pesde package source
     #[derive(Debug, Error)]
     #[non_exhaustive]
@@ -488,6 +835,14 @@ pub mod errors {
         #[error("error downloading package")]
         Download(#[from] reqwest::Error),
 
+        /// The archive is larger than the index allows
+        #[error("archive exceeds the maximum size of {0} bytes")]
+        ArchiveTooLarge(u64),
+
+        /// The downloaded archive doesn't match the integrity hash in the index
+        #[error("archive downloaded from {0} failed the integrity check")]
+        Integrity(String),
+
         /// Error unpacking package
         #[error("error unpacking package")]
         Unpack(#[source] std::io::Error),
@@ -1,4 +1,7 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -27,7 +30,21 @@ pub struct PesdePackageRef {
     pub dependencies: BTreeMap<String, (DependencySpecifiers, DependencyType)>,
     /// The target of the package
     pub target: Target,
+    /// The hex encoded SHA-256 hash of the package's archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+}
+impl PesdePackageRef {
+    /// The path of the cached file system of this package in the CAS
+    pub(crate) fn cas_index_path(&self, cas_dir: &Path) -> PathBuf {
+        cas_dir
+            .join("index")
+            .join(self.name.escaped())
+            .join(self.version.to_string())
+            .join(self.target.to_string())
+    }
 }
+
 impl PackageRef for PesdePackageRef {
     fn dependencies(&self) -> &BTreeMap<String, (DependencySpecifiers, DependencyType)> {
         &self.dependencies
@@ -0,0 +1,243 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The trailer in index commit messages which contains the signature of the commit's tree
+pub const SIGNATURE_TRAILER: &str = "pesde-signature";
+
+/// The data signed for an index commit. The parent commit is included so that a signature
+/// can't be replayed to roll the index back to an older tree
+pub fn signed_payload(tree_id: &str, parent_id: Option<&str>) -> String {
+    format!("tree {tree_id}\nparent {}\n", parent_id.unwrap_or_default())
+}
+
+/// A key used by registries to sign index commits
+#[derive(Debug)]
+pub struct SigningKey(Ed25519KeyPair);
+
+impl SigningKey {
+    /// Parses a base64 encoded PKCS#8 Ed25519 private key
+    pub fn from_base64<S: AsRef<str>>(s: S) -> Result<Self, errors::SigningKeyError> {
+        let der = STANDARD.decode(s.as_ref().trim())?;
+
+        Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+            .map(Self)
+            .map_err(|e| errors::SigningKeyError::Rejected(e.to_string()))
+    }
+
+    /// The base64 encoded public key, in the format it is advertised in the index config
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.0.public_key().as_ref())
+    }
+
+    /// Signs a tree id and the id of its commit's parent, returning the base64 encoded signature
+    pub fn sign(&self, tree_id: &str, parent_id: Option<&str>) -> String {
+        STANDARD.encode(
+            self.0
+                .sign(signed_payload(tree_id, parent_id).as_bytes())
+                .as_ref(),
+        )
+    }
+
+    /// Appends the signature trailer for the given tree id and parent commit id to a commit message
+    pub fn sign_message(&self, message: &str, tree_id: &str, parent_id: Option<&str>) -> String {
+        format!(
+            "{message}\n\n{SIGNATURE_TRAILER}: {}",
+            self.sign(tree_id, parent_id)
+        )
+    }
+}
+
+/// The keys a client trusts to sign an index, pinned on first use, along with the last
+/// index commit verified with them
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TrustedKeys {
+    /// The base64 encoded public keys
+    #[serde(default)]
+    pub keys: BTreeSet<String>,
+    /// The id of the last verified index commit, which later commits must descend from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+}
+
+/// Extracts the signature from an index commit message
+pub fn signature_from_message(message: &str) -> Option<&str> {
+    message.lines().rev().find_map(|line| {
+        line.strip_prefix(SIGNATURE_TRAILER)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(str::trim)
+    })
+}
+
+/// Whether the signature is a valid signature of the tree id and parent commit id by any of the keys
+pub fn verify<'a, I: IntoIterator<Item = &'a String>>(
+    keys: I,
+    tree_id: &str,
+    parent_id: Option<&str>,
+    signature: &str,
+) -> bool {
+    let Ok(signature) = STANDARD.decode(signature) else {
+        return false;
+    };
+
+    let payload = signed_payload(tree_id, parent_id);
+
+    keys.into_iter()
+        .filter_map(|key| STANDARD.decode(key).ok())
+        .any(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(payload.as_bytes(), &signature)
+                .is_ok()
+        })
+}
+
+/// Errors that can occur when signing index commits
+pub mod errors {
+    use thiserror::Error;
+
+    /// Errors that can occur when parsing a signing key
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum SigningKeyError {
+        /// The key is not valid base64
+        #[error("signing key is not valid base64")]
+        Base64(#[from] base64::DecodeError),
+
+        /// The key was rejected
+        #[error("signing key was rejected: {0}")]
+        Rejected(String),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    const TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+    const PARENT: &str = "3f1c8e2a9b7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f";
+
+    fn generate_key() -> SigningKey {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        SigningKey::from_base64(STANDARD.encode(pkcs8.as_ref())).unwrap()
+    }
+
+    fn verify_message(
+        keys: &[String],
+        tree_id: &str,
+        parent_id: Option<&str>,
+        message: &str,
+    ) -> bool {
+        signature_from_message(message)
+            .is_some_and(|signature| verify(keys, tree_id, parent_id, signature))
+    }
+
+    #[test]
+    fn round_trips() {
+        let key = generate_key();
+        let message = key.sign_message("add test/hello@1.0.0", TREE, Some(PARENT));
+
+        assert!(verify_message(
+            &[key.public_key()],
+            TREE,
+            Some(PARENT),
+            &message
+        ));
+
+        let root = key.sign_message("initial commit", TREE, None);
+        assert!(verify_message(&[key.public_key()], TREE, None, &root));
+    }
+
+    #[test]
+    fn rejects_other_keys() {
+        let key = generate_key();
+        let other = generate_key();
+        let message = key.sign_message("add test/hello@1.0.0", TREE, Some(PARENT));
+
+        assert!(!verify_message(
+            &[other.public_key()],
+            TREE,
+            Some(PARENT),
+            &message
+        ));
+        assert!(!verify_message(&[], TREE, Some(PARENT), &message));
+        assert!(verify_message(
+            &[other.public_key(), key.public_key()],
+            TREE,
+            Some(PARENT),
+            &message
+        ));
+    }
+
+    #[test]
+    fn rejects_replayed_signatures() {
+        let key = generate_key();
+        let message = key.sign_message("add test/hello@1.0.0", TREE, Some(PARENT));
+
+        assert!(!verify_message(&[key.public_key()], TREE, None, &message));
+        assert!(!verify_message(
+            &[key.public_key()],
+            TREE,
+            Some("0000000000000000000000000000000000000000"),
+            &message
+        ));
+        assert!(!verify_message(
+            &[key.public_key()],
+            PARENT,
+            Some(PARENT),
+            &message
+        ));
+    }
+
+    #[test]
+    fn accepts_rotations_signed_by_the_old_key() {
+        let old = generate_key();
+        let new = generate_key();
+
+        // the commit advertising the new key is signed by the old one, which the client trusts
+        let rotation = old.sign_message("rotate signing key", TREE, Some(PARENT));
+        assert!(verify_message(
+            &[old.public_key()],
+            TREE,
+            Some(PARENT),
+            &rotation
+        ));
+
+        // after re-pinning, later commits are signed by the new key only
+        let next = new.sign_message("add test/hello@1.0.1", PARENT, Some(TREE));
+        assert!(verify_message(
+            &[new.public_key()],
+            PARENT,
+            Some(TREE),
+            &next
+        ));
+        assert!(!verify_message(
+            &[old.public_key()],
+            PARENT,
+            Some(TREE),
+            &next
+        ));
+
+        // a rotation signed by the new key alone isn't trusted
+        let forged = new.sign_message("rotate signing key", TREE, Some(PARENT));
+        assert!(!verify_message(
+            &[old.public_key()],
+            TREE,
+            Some(PARENT),
+            &forged
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(matches!(
+            SigningKey::from_base64("not base64!"),
+            Err(errors::SigningKeyError::Base64(_))
+        ));
+        assert!(matches!(
+            SigningKey::from_base64(STANDARD.encode("not a key")),
+            Err(errors::SigningKeyError::Rejected(_))
+        ));
+    }
+}
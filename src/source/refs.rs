@@ -3,10 +3,13 @@ use crate::{
     source::{pesde, specifiers::DependencySpecifiers, traits::PackageRef, PackageSources},
 };
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 /// All possible package references
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case", tag = "ref_ty")]
 pub enum PackageRefs {
     /// A pesde package reference
@@ -30,6 +33,17 @@ impl PackageRefs {
             _ => false,
         }
     }
+
+    /// The path of the cached file system of this package in the CAS, if it is stored there
+    pub(crate) fn cas_index_path(&self, cas_dir: &Path) -> Option<PathBuf> {
+        match self {
+            PackageRefs::Pesde(pkg_ref) => Some(pkg_ref.cas_index_path(cas_dir)),
+            #[cfg(feature = "wally-compat")]
+            PackageRefs::Wally(pkg_ref) => Some(pkg_ref.cas_index_path(cas_dir)),
+            PackageRefs::Git(pkg_ref) => Some(pkg_ref.cas_index_path(cas_dir)),
+            PackageRefs::Workspace(_) => None,
+        }
+    }
 }
 
 impl PackageRef for PackageRefs {
@@ -1,5 +1,6 @@
 #![allow(async_fn_in_trait)]
 use crate::{
+    download::DownloadProgressReporter,
     manifest::{
         target::{Target, TargetKind},
         DependencyType,
@@ -10,6 +11,7 @@ use crate::{
 use std::{
     collections::{BTreeMap, HashSet},
     fmt::{Debug, Display},
+    sync::Arc,
 };
 
 /// A specifier for a dependency
@@ -58,5 +60,6 @@ pub trait PackageSource: Debug {
         pkg_ref: &Self::Ref,
         project: &Project,
         reqwest: &reqwest::Client,
+        reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError>;
 }
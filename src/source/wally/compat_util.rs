@@ -1,6 +1,6 @@
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 use serde::Deserialize;
 use tempfile::TempDir;
 
@@ -19,6 +19,100 @@ struct SourcemapNode {
     file_paths: Vec<RelativePathBuf>,
 }
 
+#[derive(Deserialize)]
+struct RojoProject {
+    tree: RojoNode,
+}
+
+#[derive(Deserialize)]
+struct RojoNode {
+    #[serde(default, rename = "$path")]
+    path: Option<RojoPath>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RojoPath {
+    Required(RelativePathBuf),
+    Optional { optional: RelativePathBuf },
+}
+
+impl RojoPath {
+    fn path(&self) -> &RelativePath {
+        match self {
+            RojoPath::Required(path) => path,
+            RojoPath::Optional { optional } => optional,
+        }
+    }
+}
+
+const ROJO_PROJECT_FILE_NAME: &str = "default.project.json";
+
+/// The files Rojo turns into the script of a directory, in order of precedence
+const ROJO_INIT_FILES: &[&str] = &[
+    "init.luau",
+    "init.lua",
+    "init.server.luau",
+    "init.server.lua",
+    "init.client.luau",
+    "init.client.lua",
+];
+
+/// Finds the file Rojo would turn into the root script of the package's project
+pub async fn find_rojo_lib_path(
+    package_dir: &Path,
+) -> Result<Option<RelativePathBuf>, errors::FindLibPathError> {
+    let mut project_file = RelativePathBuf::from(ROJO_PROJECT_FILE_NAME);
+    let mut visited = HashSet::new();
+
+    loop {
+        if !visited.insert(project_file.clone()) {
+            log::warn!("cyclic project files in {}", package_dir.display());
+            return Ok(None);
+        }
+
+        let contents = match fs::read_to_string(project_file.to_path(package_dir)).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(errors::FindLibPathError::ReadRojoProject(project_file, e)),
+        };
+        let rojo_project: RojoProject = serde_json::from_str(&contents)?;
+
+        let Some(path) = rojo_project.tree.path else {
+            return Ok(None);
+        };
+        let path = project_file
+            .parent()
+            .unwrap_or(RelativePath::new(""))
+            .join_normalized(path.path());
+
+        // the root can be another project, which is resolved relative to itself
+        if path.as_str().ends_with(".project.json") {
+            project_file = path;
+            continue;
+        }
+
+        if fs::metadata(path.to_path(package_dir))
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            for init_file in ROJO_INIT_FILES {
+                let init_path = path.join(init_file);
+                if fs::metadata(init_path.to_path(package_dir)).await.is_ok() {
+                    return Ok(Some(init_path));
+                }
+            }
+
+            return Ok(None);
+        }
+
+        return Ok(path
+            .extension()
+            .is_some_and(|ext| ext == "lua" || ext == "luau")
+            .then_some(path));
+    }
+}
+
 pub(crate) async fn find_lib_path(
     project: &Project,
     package_dir: &Path,
@@ -29,8 +123,7 @@ pub(crate) async fn find_lib_path(
         .scripts
         .get(&ScriptName::SourcemapGenerator.to_string())
     else {
-        log::warn!("no sourcemap generator script found in manifest");
-        return Ok(None);
+        return find_rojo_lib_path(package_dir).await;
     };
 
     let result = execute_script(
@@ -41,18 +134,23 @@ pub(crate) async fn find_lib_path(
         true,
     )?;
 
-    if let Some(result) = result.filter(|result| !result.is_empty()) {
-        let node: SourcemapNode = serde_json::from_str(&result)?;
-        Ok(node.file_paths.into_iter().find(|path| {
-            path.extension()
-                .is_some_and(|ext| ext == "lua" || ext == "luau")
-        }))
-    } else {
-        Ok(None)
-    }
+    // the script doesn't output anything if its runtime isn't installed
+    let Some(result) = result.filter(|result| !result.trim().is_empty()) else {
+        log::debug!(
+            "sourcemap generator script produced no output, finding lib path from rojo project"
+        );
+        return find_rojo_lib_path(package_dir).await;
+    };
+
+    let node: SourcemapNode = serde_json::from_str(&result)?;
+    Ok(node.file_paths.into_iter().find(|path| {
+        path.extension()
+            .is_some_and(|ext| ext == "lua" || ext == "luau")
+    }))
 }
 
-pub(crate) const WALLY_MANIFEST_FILE_NAME: &str = "wally.toml";
+/// The file name of the Wally manifest
+pub const WALLY_MANIFEST_FILE_NAME: &str = "wally.toml";
 
 pub(crate) async fn get_target(
     project: &Project,
@@ -74,7 +172,9 @@ pub(crate) async fn get_target(
     })
 }
 
+/// Errors that can occur when using Wally compatibility utilities
 pub mod errors {
+    use relative_path::RelativePathBuf;
     use thiserror::Error;
 
     /// Errors that can occur when finding the lib path
@@ -89,10 +189,14 @@ pub mod errors {
         #[error("error executing sourcemap generator script")]
         Script(#[from] std::io::Error),
 
-        /// An error occurred while deserializing the sourcemap result
-        #[error("error deserializing sourcemap result")]
+        /// An error occurred while deserializing the sourcemap result or Rojo project
+        #[error("error deserializing sourcemap result or Rojo project")]
         Serde(#[from] serde_json::Error),
 
+        /// An error occurred while reading a Rojo project file
+        #[error("error reading Rojo project file {0}")]
+        ReadRojoProject(RelativePathBuf, #[source] std::io::Error),
+
         /// An error occurred while deserializing the wally manifest
         #[error("error deserializing wally manifest")]
         WallyManifest(#[from] toml::de::Error),
@@ -9,24 +9,44 @@ use crate::{
     source::{specifiers::DependencySpecifiers, wally::specifier::WallyDependencySpecifier},
 };
 
+/// The realm a Wally package runs in
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum Realm {
+    /// The package can run on both the client and the server
     #[serde(alias = "dev")]
     Shared,
+    /// The package can only run on the server
     Server,
 }
 
+/// The `[package]` section of a Wally manifest
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct WallyPackage {
+    /// The name of the package
     pub name: WallyPackageName,
+    /// The version of the package
     pub version: Version,
+    /// The registry the package is published to
     pub registry: url::Url,
+    /// The realm of the package
     pub realm: Realm,
+    /// The description of the package
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The license of the package
+    #[serde(default)]
+    pub license: Option<String>,
+    /// The authors of the package
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// Whether the package is private
+    #[serde(default)]
+    pub private: bool,
 }
 
-pub fn deserialize_specifiers<'de, D: Deserializer<'de>>(
+fn deserialize_specifiers<'de, D: Deserializer<'de>>(
     deserializer: D,
 ) -> Result<BTreeMap<String, WallyDependencySpecifier>, D::Error> {
     // specifier is in form of `name@version_req`
@@ -49,14 +69,19 @@ pub fn deserialize_specifiers<'de, D: Deserializer<'de>>(
         .collect()
 }
 
+/// A Wally manifest
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct WallyManifest {
+    /// The package section of the manifest
     pub package: WallyPackage,
+    /// The dependencies of the package
     #[serde(default, deserialize_with = "deserialize_specifiers")]
     pub dependencies: BTreeMap<String, WallyDependencySpecifier>,
+    /// The server dependencies of the package
     #[serde(default, deserialize_with = "deserialize_specifiers")]
     pub server_dependencies: BTreeMap<String, WallyDependencySpecifier>,
+    /// The dev dependencies of the package
     #[serde(default, deserialize_with = "deserialize_specifiers")]
     pub dev_dependencies: BTreeMap<String, WallyDependencySpecifier>,
 }
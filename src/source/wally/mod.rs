@@ -1,8 +1,9 @@
 use crate::{
+    download::{response_reader, send_with_retries, DownloadProgressReporter},
     manifest::target::{Target, TargetKind},
     names::PackageNames,
     source::{
-        fs::{store_in_cas, FSEntry, PackageFS},
+        fs::{store_in_cas, write_atomically, FSEntry, LinkMode, PackageFS},
         git_index::{read_file, root_tree, GitBasedSource},
         traits::PackageSource,
         version_id::VersionId,
@@ -25,19 +26,31 @@ use serde::Deserialize;
 use std::{
     collections::{BTreeMap, HashSet},
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tempfile::{tempdir, tempfile};
+use tokio::{
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex,
+    task::spawn_blocking,
 };
-use tempfile::tempdir;
-use tokio::{io::AsyncWriteExt, sync::Mutex, task::spawn_blocking};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 
-pub(crate) mod compat_util;
-pub(crate) mod manifest;
+/// Utilities for Wally compatibility
+pub mod compat_util;
+/// The Wally manifest
+pub mod manifest;
 /// The Wally package reference
 pub mod pkg_ref;
 /// The Wally dependency specifier
 pub mod specifier;
 
+/// The maximum size of a downloaded Wally package archive, since Wally indices don't advertise one
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
 /// The Wally package source
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct WallyPackageSource {
@@ -85,6 +98,27 @@ impl WallyPackageSource {
         .await
         .unwrap()
     }
+
+    /// Adds the headers the Wally API expects, and the token for this index if there is one, to a request
+    pub fn prepare_request(
+        &self,
+        project: &Project,
+        mut request: reqwest::RequestBuilder,
+    ) -> reqwest::RequestBuilder {
+        request = request.header(
+            "Wally-Version",
+            std::env::var("PESDE_WALLY_VERSION")
+                .as_deref()
+                .unwrap_or("0.3.2"),
+        );
+
+        if let Some(token) = project.auth_config.tokens().get(&self.repo_url) {
+            log::debug!("using token for {}", self.repo_url);
+            request = request.header(AUTHORIZATION, token);
+        }
+
+        request
+    }
 }
 
 impl PackageSource for WallyPackageSource {
@@ -197,13 +231,9 @@ impl PackageSource for WallyPackageSource {
         pkg_ref: &Self::Ref,
         project: &Project,
         reqwest: &reqwest::Client,
+        reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError> {
-        let config = self.config(project).await.map_err(Box::new)?;
-        let index_file = project
-            .cas_dir
-            .join("wally_index")
-            .join(pkg_ref.name.escaped())
-            .join(pkg_ref.version.to_string());
+        let index_file = pkg_ref.cas_index_path(project.cas_dir());
 
         let tempdir = match fs::read_to_string(&index_file).await {
             Ok(s) => {
@@ -216,7 +246,8 @@ impl PackageSource for WallyPackageSource {
                 let tempdir = tempdir()?;
                 let fs = toml::from_str::<PackageFS>(&s)?;
 
-                fs.write_to(&tempdir, project.cas_dir(), false).await?;
+                fs.write_to(&tempdir, project.cas_dir(), LinkMode::Copy)
+                    .await?;
 
                 return Ok((fs, get_target(project, &tempdir).await?));
             }
@@ -224,31 +255,60 @@ impl PackageSource for WallyPackageSource {
             Err(e) => return Err(errors::DownloadError::ReadIndex(e)),
         };
 
+        let config = self.config(project).await.map_err(Box::new)?;
         let (scope, name) = pkg_ref.name.as_str();
 
-        let mut request = reqwest
-            .get(format!(
+        let request = self.prepare_request(
+            project,
+            reqwest.get(format!(
                 "{}/v1/package-contents/{scope}/{name}/{}",
-                config.api.as_str().trim_end_matches('/'),
+                config.api(),
                 pkg_ref.version
-            ))
-            .header(
-                "Wally-Version",
-                std::env::var("PESDE_WALLY_VERSION")
-                    .as_deref()
-                    .unwrap_or("0.3.2"),
-            );
+            )),
+        );
 
-        if let Some(token) = project.auth_config.tokens().get(&self.repo_url) {
-            log::debug!("using token for {}", self.repo_url);
-            request = request.header(AUTHORIZATION, token);
+        let response = send_with_retries(request, project.download_config().max_retries()).await?;
+
+        if response
+            .content_length()
+            .is_some_and(|len| len > MAX_ARCHIVE_SIZE)
+        {
+            return Err(errors::DownloadError::ArchiveTooLarge(MAX_ARCHIVE_SIZE));
         }
 
-        let response = request.send().await?.error_for_status()?;
-        let mut bytes = response.bytes().await?;
+        // zip archives need to be seekable, so they're spooled to disk instead of kept in memory
+        let mut archive_file = tokio::fs::File::from_std(tempfile()?);
+        let size = Arc::new(AtomicU64::new(0));
+        let copied = tokio::io::copy_buf(
+            &mut response_reader(response, {
+                let size = size.clone();
+
+                move |chunk| {
+                    let len = chunk.len() as u64;
+                    if size.fetch_add(len, Ordering::Relaxed) + len > MAX_ARCHIVE_SIZE {
+                        return Err(std::io::Error::other("archive exceeds the maximum size"));
+                    }
+
+                    reporter.report_progress(len);
+
+                    Ok(())
+                }
+            }),
+            &mut archive_file,
+        )
+        .await;
+
+        match copied {
+            Ok(_) => {}
+            Err(_) if size.load(Ordering::Relaxed) > MAX_ARCHIVE_SIZE => {
+                return Err(errors::DownloadError::ArchiveTooLarge(MAX_ARCHIVE_SIZE))
+            }
+            Err(e) => return Err(e.into()),
+        }
+        archive_file.rewind().await?;
 
         let archive = async_zip::tokio::read::seek::ZipFileReader::with_tokio(
-            std::io::Cursor::new(&mut bytes),
+            tokio::io::BufReader::new(archive_file),
         )
         .await?;
 
@@ -319,7 +379,7 @@ impl PackageSource for WallyPackageSource {
                 .map_err(errors::DownloadError::WriteIndex)?;
         }
 
-        fs::write(&index_file, toml::to_string(&fs)?)
+        write_atomically(project.cas_dir(), &index_file, toml::to_string(&fs)?)
             .await
             .map_err(errors::DownloadError::WriteIndex)?;
 
@@ -335,6 +395,13 @@ pub struct WallyIndexConfig {
     fallback_registries: Vec<gix::Url>,
 }
 
+impl WallyIndexConfig {
+    /// The URL of the API
+    pub fn api(&self) -> &str {
+        self.api.as_str().trim_end_matches('/')
+    }
+}
+
 /// Errors that can occur when interacting with a Wally package source
 pub mod errors {
     use thiserror::Error;
@@ -430,6 +497,10 @@ pub mod errors {
         #[error("error reading index file")]
         ReadIndex(#[source] std::io::Error),
 
+        /// The archive is larger than the maximum size
+        #[error("archive exceeds the maximum size of {0} bytes")]
+        ArchiveTooLarge(u64),
+
         /// Error decompressing archive
         #[error("error decompressing archive")]
         Decompress(#[from] async_zip::error::ZipError),
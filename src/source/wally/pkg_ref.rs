@@ -1,4 +1,7 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -27,6 +30,16 @@ pub struct WallyPackageRef {
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub dependencies: BTreeMap<String, (DependencySpecifiers, DependencyType)>,
 }
+impl WallyPackageRef {
+    /// The path of the cached file system of this package in the CAS
+    pub(crate) fn cas_index_path(&self, cas_dir: &Path) -> PathBuf {
+        cas_dir
+            .join("wally_index")
+            .join(self.name.escaped())
+            .join(self.version.to_string())
+    }
+}
+
 impl PackageRef for WallyPackageRef {
     fn dependencies(&self) -> &BTreeMap<String, (DependencySpecifiers, DependencyType)> {
         &self.dependencies
@@ -1,4 +1,5 @@
 use crate::{
+    download::DownloadProgressReporter,
     manifest::target::{Target, TargetKind},
     names::PackageNames,
     source::{
@@ -11,7 +12,10 @@ use crate::{
 use futures::StreamExt;
 use relative_path::RelativePathBuf;
 use reqwest::Client;
-use std::collections::{BTreeMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
 use tokio::pin;
 
 /// The workspace package reference
@@ -131,6 +135,7 @@ impl PackageSource for WorkspacePackageSource {
         pkg_ref: &Self::Ref,
         project: &Project,
         _reqwest: &Client,
+        _reporter: Arc<dyn DownloadProgressReporter>,
     ) -> Result<(PackageFS, Target), Self::DownloadError> {
         let path = pkg_ref.path.to_path(project.workspace_dir.clone().unwrap());
 
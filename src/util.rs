@@ -1,5 +1,5 @@
-use crate::AuthConfig;
-use gix::bstr::BStr;
+use crate::{AuthConfig, GitCredentials, Project};
+use gix::{bstr::BStr, sec::identity::Account};
 use serde::{Deserialize, Deserializer, Serializer};
 use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, HashSet};
@@ -12,18 +12,76 @@ pub fn authenticate_conn(
     >,
     auth_config: &AuthConfig,
 ) {
-    if let Some(iden) = auth_config.git_credentials().cloned() {
-        conn.set_credentials(move |action| match action {
-            gix::credentials::helper::Action::Get(ctx) => {
-                Ok(Some(gix::credentials::protocol::Outcome {
-                    identity: iden.clone(),
-                    next: gix::credentials::helper::NextAction::from(ctx),
-                }))
-            }
-            gix::credentials::helper::Action::Store(_) => Ok(None),
-            gix::credentials::helper::Action::Erase(_) => Ok(None),
-        });
+    let Some(url) = conn.remote().url(gix::remote::Direction::Fetch) else {
+        return;
+    };
+
+    if let Some(credentials) = credentials_for(url, auth_config) {
+        conn.set_credentials(credentials);
+    }
+}
+
+/// The credentials callback for the configured Git credentials of the URL, if there are any
+pub fn credentials_for(
+    url: &gix::Url,
+    auth_config: &AuthConfig,
+) -> Option<gix::remote::AuthenticateFn<'static>> {
+    let iden = match auth_config.git_credentials_for(url) {
+        Some(GitCredentials::Password(account)) => account,
+        Some(GitCredentials::Token(token)) => Account {
+            username: "x-access-token".to_string(),
+            password: token,
+        },
+        // SSH credentials are applied through `git_config_overrides`
+        _ => return None,
+    };
+
+    Some(Box::new(move |action| match action {
+        gix::credentials::helper::Action::Get(ctx) => {
+            Ok(Some(gix::credentials::protocol::Outcome {
+                identity: iden.clone(),
+                next: gix::credentials::helper::NextAction::from(ctx),
+            }))
+        }
+        gix::credentials::helper::Action::Store(_) => Ok(None),
+        gix::credentials::helper::Action::Erase(_) => Ok(None),
+    }))
+}
+
+pub fn git_config_overrides(url: &gix::Url, project: &Project) -> Vec<String> {
+    fn quote(path: &std::path::Path) -> String {
+        format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+    }
+
+    let mut overrides = vec![];
+
+    match project.auth_config().git_credentials_for(url) {
+        Some(GitCredentials::SshKey(path)) => overrides.push(format!(
+            "core.sshCommand=ssh -i {} -o IdentitiesOnly=yes",
+            quote(&path)
+        )),
+        Some(GitCredentials::SshAgent(Some(socket))) => overrides.push(format!(
+            "core.sshCommand=ssh -o IdentityAgent={}",
+            quote(&socket)
+        )),
+        _ => {}
+    }
+
+    let network_config = project.network_config();
+
+    if let Some(proxy) = network_config.proxy() {
+        overrides.push(format!("http.proxy={proxy}"));
+    }
+
+    if let Some(no_proxy) = network_config.no_proxy() {
+        overrides.push(format!("gitoxide.http.noProxy={no_proxy}"));
+    }
+
+    if let Some(ca_file) = network_config.ca_file() {
+        overrides.push(format!("http.sslCAInfo={}", ca_file.display()));
     }
+
+    overrides
 }
 
 pub fn serialize_gix_url<S: Serializer>(url: &gix::Url, serializer: S) -> Result<S::Ok, S::Error> {
@@ -37,6 +95,14 @@ pub fn deserialize_gix_url<'de, D: Deserializer<'de>>(
     gix::Url::from_bytes(BStr::new(&s)).map_err(serde::de::Error::custom)
 }
 
+pub fn deserialize_gix_url_option<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<gix::Url>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| gix::Url::from_bytes(BStr::new(&s)).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
 pub fn deserialize_gix_url_map<'de, D: Deserializer<'de>>(
     deserializer: D,
 ) -> Result<BTreeMap<String, gix::Url>, D::Error> {
@@ -83,3 +149,74 @@ pub fn deserialize_git_like_url<'de, D: Deserializer<'de>>(
 pub fn hash<S: AsRef<[u8]>>(struc: S) -> String {
     format!("{:x}", Sha256::digest(struc.as_ref()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkConfig;
+    use std::path::PathBuf;
+
+    fn project(auth_config: AuthConfig) -> Project {
+        Project::new("/project", None::<PathBuf>, "/data", "/cas", auth_config)
+    }
+
+    fn url(url: &str) -> gix::Url {
+        url.try_into().unwrap()
+    }
+
+    #[test]
+    fn overrides_ssh_command() {
+        let project = project(AuthConfig::new().with_host_git_credentials([
+            (
+                "github.com".to_string(),
+                GitCredentials::SshKey("/home/user/.ssh/it's a key".into()),
+            ),
+            (
+                "gitlab.com".to_string(),
+                GitCredentials::SshAgent(Some("/run/agent.sock".into())),
+            ),
+            ("bitbucket.org".to_string(), GitCredentials::SshAgent(None)),
+            (
+                "git.example.com".to_string(),
+                GitCredentials::Token("token".to_string()),
+            ),
+        ]));
+
+        assert_eq!(
+            git_config_overrides(&url("git@github.com:acme/package.git"), &project),
+            ["core.sshCommand=ssh -i '/home/user/.ssh/it'\\''s a key' -o IdentitiesOnly=yes"]
+        );
+        assert_eq!(
+            git_config_overrides(&url("git@gitlab.com:acme/package.git"), &project),
+            ["core.sshCommand=ssh -o IdentityAgent='/run/agent.sock'"]
+        );
+        assert!(
+            git_config_overrides(&url("git@bitbucket.org:acme/package.git"), &project).is_empty()
+        );
+        assert!(
+            git_config_overrides(&url("https://git.example.com/acme/package"), &project).is_empty()
+        );
+        assert!(
+            git_config_overrides(&url("https://codeberg.org/acme/package"), &project).is_empty()
+        );
+    }
+
+    #[test]
+    fn overrides_network_config() {
+        let project = project(AuthConfig::new()).with_network_config(
+            NetworkConfig::new()
+                .with_proxy(Some("http://proxy.example.com:8080".to_string()))
+                .with_no_proxy(Some("localhost,.internal.example.com".to_string()))
+                .with_ca_file(Some("/etc/ssl/certs/ca.pem".into())),
+        );
+
+        assert_eq!(
+            git_config_overrides(&url("https://github.com/pesde-pkg/index"), &project),
+            [
+                "http.proxy=http://proxy.example.com:8080",
+                "gitoxide.http.noProxy=localhost,.internal.example.com",
+                "http.sslCAInfo=/etc/ssl/certs/ca.pem",
+            ]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    lockfile::{DownloadedGraph, Graph},
+    manifest::target::Target,
+    refresh_sources,
+    source::{
+        fs::LinkMode,
+        refs::PackageRefs,
+        traits::{PackageRef, PackageSource},
+    },
+    Project,
+};
+use fs_err::tokio as fs;
+use relative_path::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
+
+/// The folder vendored packages are stored in
+pub const VENDOR_FOLDER: &str = "vendor";
+/// The file in the vendor folder which lists the vendored packages
+pub const VENDOR_CONFIG_FILE: &str = "vendor.toml";
+
+/// A package vendored into a project
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VendoredPackage {
+    /// The folder the package is stored in, relative to the vendor folder
+    pub path: RelativePathBuf,
+    /// The target of the package
+    pub target: Target,
+    /// The reference of the package the vendored copy was made from
+    pub pkg_ref: PackageRefs,
+}
+
+/// The packages vendored into a project
+pub type VendorConfig = Graph<VendoredPackage>;
+
+/// Recursively copies a directory, creating real files
+pub(crate) async fn copy_dir(src: PathBuf, dst: PathBuf) -> std::io::Result<()> {
+    let mut dirs = vec![(src, dst)];
+
+    while let Some((src, dst)) = dirs.pop() {
+        fs::create_dir_all(&dst).await?;
+
+        let mut read_dir = fs::read_dir(&src).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let dst = dst.join(entry.file_name());
+
+            if entry.file_type().await?.is_dir() {
+                dirs.push((entry.path(), dst));
+            } else {
+                fs::copy(entry.path(), dst).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Project {
+    /// The folder vendored packages are stored in
+    pub fn vendor_dir(&self) -> PathBuf {
+        self.package_dir().join(VENDOR_FOLDER)
+    }
+
+    /// Deserialize the vendor config, if the project's dependencies are vendored
+    pub async fn deser_vendor_config(
+        &self,
+    ) -> Result<Option<VendorConfig>, errors::VendorConfigReadError> {
+        match fs::read_to_string(self.vendor_dir().join(VENDOR_CONFIG_FILE)).await {
+            Ok(string) => Ok(Some(toml::from_str(&string)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Copies every package in the graph into the vendor folder, replacing what was vendored
+    /// before. Later installs use the vendored copies instead of downloading the packages.
+    /// Only the packages listed in the vendor config are removed, other files in the vendor
+    /// folder are kept. The vendor folder is only changed once every package was downloaded
+    pub async fn vendor(
+        &self,
+        graph: &DownloadedGraph,
+        reqwest: &reqwest::Client,
+    ) -> Result<VendorConfig, errors::VendorError> {
+        let vendor_dir = self.vendor_dir();
+
+        let old_paths = self
+            .deser_vendor_config()
+            .await?
+            .unwrap_or_default()
+            .into_values()
+            .flat_map(|versions| versions.into_values())
+            .map(|package| package.path)
+            .collect::<HashSet<_>>();
+
+        let packages = graph
+            .iter()
+            .flat_map(|(name, versions)| {
+                versions.iter().map(move |(version_id, node)| {
+                    let path = RelativePathBuf::from(name.escaped()).join(version_id.escaped());
+                    (name, version_id, node, path)
+                })
+            })
+            // workspace members are part of the repository already
+            .filter(|(_, _, node, _)| !matches!(node.node.pkg_ref, PackageRefs::Workspace(_)))
+            .collect::<Vec<_>>();
+
+        for (_, _, _, path) in &packages {
+            if !old_paths.contains(path) && fs::metadata(path.to_path(&vendor_dir)).await.is_ok() {
+                return Err(errors::VendorError::PathTaken(path.clone()));
+            }
+        }
+
+        refresh_sources(
+            self,
+            packages
+                .iter()
+                .map(|(_, _, node, _)| node.node.pkg_ref.source()),
+            &mut HashSet::new(),
+        )
+        .await?;
+
+        // packages are downloaded here first, so a failed download doesn't leave the vendor
+        // folder half replaced
+        fs::create_dir_all(&vendor_dir).await?;
+        let staging_dir = tempfile::Builder::new()
+            .prefix(".vendor")
+            .tempdir_in(&vendor_dir)?;
+
+        let mut config = VendorConfig::new();
+
+        for (name, version_id, node, path) in packages {
+            let (package_fs, _) = node
+                .node
+                .pkg_ref
+                .source()
+                .download(&node.node.pkg_ref, self, reqwest, Arc::new(()))
+                .await
+                .map_err(|e| {
+                    errors::VendorError::Download(format!("{name}@{version_id}"), Box::new(e))
+                })?;
+
+            package_fs
+                .write_to(
+                    path.to_path(staging_dir.path()),
+                    self.cas_dir(),
+                    LinkMode::Copy,
+                )
+                .await?;
+
+            log::debug!("vendored {name}@{version_id} into {path}");
+
+            config.entry(name.clone()).or_default().insert(
+                version_id.clone(),
+                VendoredPackage {
+                    path,
+                    target: node.target.clone(),
+                    pkg_ref: node.node.pkg_ref.clone(),
+                },
+            );
+        }
+
+        for path in &old_paths {
+            let path = path.to_path(&vendor_dir);
+
+            match fs::remove_dir_all(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // remove the package's folder if this was its last vendored version
+            if let Some(parent) = path.parent().filter(|parent| *parent != vendor_dir) {
+                let _ = fs::remove_dir(parent).await;
+            }
+        }
+
+        for package in config.values().flat_map(|versions| versions.values()) {
+            let path = package.path.to_path(&vendor_dir);
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            fs::rename(package.path.to_path(staging_dir.path()), path).await?;
+        }
+
+        let config_path = staging_dir.path().join(VENDOR_CONFIG_FILE);
+        fs::write(&config_path, toml::to_string(&config)?).await?;
+        fs::rename(config_path, vendor_dir.join(VENDOR_CONFIG_FILE)).await?;
+
+        Ok(config)
+    }
+}
+
+/// Errors that can occur when vendoring packages
+pub mod errors {
+    use relative_path::RelativePathBuf;
+    use thiserror::Error;
+
+    /// Errors that can occur when reading the vendor config
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum VendorConfigReadError {
+        /// Error reading the vendor config
+        #[error("error reading vendor config")]
+        Io(#[from] std::io::Error),
+
+        /// Error deserializing the vendor config
+        #[error("error deserializing vendor config")]
+        Serde(#[from] toml::de::Error),
+    }
+
+    /// Errors that can occur when vendoring packages
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum VendorError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error reading the vendor config
+        #[error("error reading vendor config")]
+        VendorConfigRead(#[from] VendorConfigReadError),
+
+        /// A file which isn't a vendored package is in the way of one
+        #[error("{0} in the vendor folder isn't a vendored package, remove it to vendor the package there")]
+        PathTaken(RelativePathBuf),
+
+        /// Error refreshing a package source
+        #[error("error refreshing package source")]
+        Refresh(#[from] Box<crate::source::errors::RefreshError>),
+
+        /// Error downloading a package
+        #[error("error downloading package {0}")]
+        Download(String, #[source] Box<crate::source::errors::DownloadError>),
+
+        /// Error serializing the vendor config
+        #[error("error serializing vendor config")]
+        Serde(#[from] toml::ser::Error),
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{
+    cas::{hash_file, remove_file},
+    lockfile::DownloadedDependencyGraphNode,
+    manifest::{target::TargetKind, Manifest},
+    names::PackageNames,
+    scripts::ScriptName,
+    source::{
+        fs::{cas_path, link_file, FSEntry, LinkMode, PackageFS},
+        version_id::VersionId,
+    },
+    Project, PACKAGES_CONTAINER_NAME,
+};
+use fs_err::tokio as fs;
+use relative_path::RelativePathBuf;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// How an installed file differs from the package's original contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDifference {
+    /// The file's contents were modified
+    Modified,
+    /// The file's contents were modified in place, which also overwrote the original contents in
+    /// the CAS, since installed files are hard linked to it
+    ModifiedInCas,
+    /// The file was removed
+    Missing,
+    /// The file isn't part of the package
+    Extra,
+}
+
+impl Display for FileDifference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileDifference::Modified => write!(f, "modified"),
+            FileDifference::ModifiedInCas => write!(f, "modified in CAS"),
+            FileDifference::Missing => write!(f, "missing"),
+            FileDifference::Extra => write!(f, "extra"),
+        }
+    }
+}
+
+/// The differences between an installed package and its original contents
+#[derive(Debug, Clone)]
+pub struct InstalledPackageDiff {
+    /// The folder the package is installed in
+    pub container_folder: PathBuf,
+    /// The files which differ, relative to the container folder
+    pub files: BTreeMap<RelativePathBuf, FileDifference>,
+}
+
+impl InstalledPackageDiff {
+    /// Whether the installed package matches its original contents
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Whether the original contents of a modified file were lost
+    pub fn original_lost(&self) -> bool {
+        self.files
+            .values()
+            .any(|difference| *difference == FileDifference::ModifiedInCas)
+    }
+
+    /// Restores the installed package to its original contents by linking the differing files
+    /// from the CAS again using the given link mode, and removing the extra ones
+    pub async fn restore<P: AsRef<Path>>(
+        &self,
+        package_fs: &PackageFS,
+        cas_dir: P,
+        link_mode: LinkMode,
+    ) -> Result<(), errors::RestoreInstalledPackageError> {
+        let PackageFS::CAS(entries) = package_fs else {
+            return Ok(());
+        };
+
+        // make sure every original is intact before touching anything
+        let mut originals = BTreeMap::new();
+        for (path, difference) in &self.files {
+            if *difference == FileDifference::Extra {
+                continue;
+            }
+
+            let Some(FSEntry::File(hash)) = entries.get(path) else {
+                continue;
+            };
+
+            let original = cas_path(hash, cas_dir.as_ref());
+            match hash_file(&original).await {
+                Ok(original_hash) if original_hash == *hash => {}
+                Ok(_) => {
+                    return Err(errors::RestoreInstalledPackageError::OriginalLost(
+                        path.clone(),
+                    ))
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(errors::RestoreInstalledPackageError::OriginalLost(
+                        path.clone(),
+                    ))
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            originals.insert(path, original);
+        }
+
+        for (path, difference) in &self.files {
+            let installed_path = path.to_path(&self.container_folder);
+
+            // the installed file might be hard linked to the CAS, so it must be removed instead of
+            // overwritten
+            remove_file(&installed_path).await?;
+
+            if *difference == FileDifference::Extra {
+                continue;
+            }
+
+            let Some(original) = originals.get(path) else {
+                continue;
+            };
+
+            if let Some(parent) = installed_path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            link_file(original, &installed_path, link_mode).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the differences to a directory containing the original contents of the package,
+    /// for example to turn them into a patch
+    pub async fn apply_to<P: AsRef<Path>>(&self, directory: P) -> std::io::Result<()> {
+        for (path, difference) in &self.files {
+            let path_in_directory = path.to_path(directory.as_ref());
+
+            if *difference == FileDifference::Missing {
+                remove_file(&path_in_directory).await?;
+                continue;
+            }
+
+            if let Some(parent) = path_in_directory.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+
+            remove_file(&path_in_directory).await?;
+            fs::copy(path.to_path(&self.container_folder), path_in_directory).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The file the Roblox sync config generator writes
+const SYNC_CONFIG_FILE_NAME: &str = "default.project.json";
+
+impl Project {
+    /// The paths a package's patch changes, which are expected to differ from the original
+    #[cfg(feature = "patches")]
+    async fn patched_paths(
+        &self,
+        manifest: &Manifest,
+        name: &PackageNames,
+        version_id: &VersionId,
+    ) -> Result<HashSet<RelativePathBuf>, errors::DiffInstalledPackageError> {
+        let Some(patch_path) = manifest
+            .patches
+            .get(name)
+            .and_then(|versions| versions.get(version_id))
+        else {
+            return Ok(HashSet::new());
+        };
+
+        let patch = fs::read(patch_path.to_path(self.package_dir())).await?;
+        let patch = git2::Diff::from_buffer(&patch)?;
+
+        Ok(patch
+            .deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .filter_map(|path| RelativePathBuf::from_path(path).ok())
+            .collect())
+    }
+
+    /// Compares the installed files of a package against its original contents. Linker files and
+    /// the files a patch changes are expected, and aren't reported
+    pub async fn diff_installed_package(
+        &self,
+        manifest: &Manifest,
+        name: &PackageNames,
+        version_id: &VersionId,
+        node: &DownloadedDependencyGraphNode,
+        package_fs: &PackageFS,
+    ) -> Result<InstalledPackageDiff, errors::DiffInstalledPackageError> {
+        let container_folder = node.node.container_folder(
+            &self
+                .package_dir()
+                .join(manifest.target.kind().packages_folder(version_id.target()))
+                .join(PACKAGES_CONTAINER_NAME),
+            name,
+            version_id.version(),
+        );
+
+        let mut diff = InstalledPackageDiff {
+            container_folder,
+            files: BTreeMap::new(),
+        };
+
+        let PackageFS::CAS(entries) = package_fs else {
+            return Ok(diff);
+        };
+
+        #[cfg(feature = "patches")]
+        let patched_paths = self.patched_paths(manifest, name, version_id).await?;
+        #[cfg(not(feature = "patches"))]
+        let patched_paths = HashSet::<RelativePathBuf>::new();
+
+        for (path, entry) in entries {
+            let FSEntry::File(hash) = entry else {
+                continue;
+            };
+
+            if patched_paths.contains(path) {
+                continue;
+            }
+
+            let installed_hash = match hash_file(&path.to_path(&diff.container_folder)).await {
+                Ok(installed_hash) => installed_hash,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    diff.files.insert(path.clone(), FileDifference::Missing);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if installed_hash == *hash {
+                continue;
+            }
+
+            let original_intact = hash_file(&cas_path(hash, self.cas_dir()))
+                .await
+                .is_ok_and(|original_hash| original_hash == *hash);
+
+            diff.files.insert(
+                path.clone(),
+                if original_intact {
+                    FileDifference::Modified
+                } else {
+                    FileDifference::ModifiedInCas
+                },
+            );
+        }
+
+        // the sync config generator writes its output to the root of the package
+        let generates_sync_config = !node.node.pkg_ref.like_wally()
+            && node.target.build_files().is_some()
+            && manifest
+                .scripts
+                .contains_key(&ScriptName::RobloxSyncConfigGenerator.to_string());
+        let linker_folders = TargetKind::VARIANTS
+            .iter()
+            .map(|target| version_id.target().packages_folder(target))
+            .collect::<HashSet<_>>();
+
+        let mut dirs = vec![RelativePathBuf::new()];
+        while let Some(dir) = dirs.pop() {
+            let mut read_dir = match fs::read_dir(dir.to_path(&diff.container_folder)).await {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let path = dir.join(&file_name);
+                let is_root = dir.as_str().is_empty();
+
+                if entry.file_type().await?.is_dir() {
+                    if !(is_root && (file_name == ".git" || linker_folders.contains(&file_name))) {
+                        dirs.push(path);
+                    }
+                    continue;
+                }
+
+                if (is_root && generates_sync_config && file_name == SYNC_CONFIG_FILE_NAME)
+                    || entries.contains_key(&path)
+                    || patched_paths.contains(&path)
+                {
+                    continue;
+                }
+
+                diff.files.insert(path, FileDifference::Extra);
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Errors that can occur when verifying installed packages
+pub mod errors {
+    use relative_path::RelativePathBuf;
+    use thiserror::Error;
+
+    /// Errors that can occur when comparing an installed package against its original contents
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum DiffInstalledPackageError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// Error parsing the package's patch
+        #[cfg(feature = "patches")]
+        #[error("error parsing patch")]
+        Patch(#[from] git2::Error),
+    }
+
+    /// Errors that can occur when restoring an installed package to its original contents
+    #[derive(Debug, Error)]
+    #[non_exhaustive]
+    pub enum RestoreInstalledPackageError {
+        /// Error interacting with the filesystem
+        #[error("error interacting with the filesystem")]
+        Io(#[from] std::io::Error),
+
+        /// The original contents of a file were lost
+        #[error("the original contents of {0} were lost")]
+        OriginalLost(RelativePathBuf),
+    }
+}